@@ -0,0 +1,111 @@
+use clap::Parser;
+use pololu_motoron::{ControllerType, DeviceBuilder};
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+/// Program that ramps a motor on a given Pololu Motoron device up to full speed and back down,
+/// with acceleration limits, until stopped with Ctrl-C
+#[derive(Debug, Parser)]
+#[command(version, about)]
+struct Args {
+    /// I2C device we should connect to
+    #[arg(short, long, default_value = "/dev/i2c-0")]
+    device: PathBuf,
+
+    /// I2C address to address this device with
+    #[arg(short, long, default_value_t = 0x10)]
+    address: u8,
+
+    /// Model of the controller, such as M2T256 or M2T550. The current is only printed for
+    /// controllers that can sense it
+    #[arg(short, long, default_value = "M2T256", value_parser = parse_controller_type)]
+    controller: ControllerType,
+
+    /// Index of the motor to ramp
+    #[arg(short, long, default_value_t = 0)]
+    motor: u8,
+
+    /// Maximum acceleration and deceleration, in raw speed units per update period
+    #[arg(long, default_value_t = 10)]
+    accel: u16,
+
+    /// Logic voltage of the controller in millivolts, used to read back the current. Defaults to
+    /// the usual one for the controller
+    #[arg(long)]
+    reference_mv: Option<u16>,
+}
+
+fn parse_controller_type(name: &str) -> Result<ControllerType, String> {
+    let controller_type = match name.to_ascii_uppercase().as_str() {
+        "M1T550" => ControllerType::M1T550,
+        "M1U550" => ControllerType::M1U550,
+        "M2T550" => ControllerType::M2T550,
+        "M2U550" => ControllerType::M2U550,
+        "M1T256" => ControllerType::M1T256,
+        "M1U256" => ControllerType::M1U256,
+        "M2T256" => ControllerType::M2T256,
+        "M2U256" => ControllerType::M2U256,
+        "M3S550" => ControllerType::M3S550,
+        "M3H550" => ControllerType::M3H550,
+        "M3S256" => ControllerType::M3S256,
+        "M3H256" => ControllerType::M3H256,
+        "M2S24V14" => ControllerType::M2S24v14,
+        "M2H24V14" => ControllerType::M2H24v14,
+        "M2S24V16" => ControllerType::M2S24v16,
+        "M2H24V16" => ControllerType::M2H24v16,
+        "M2S18V18" => ControllerType::M2S18v18,
+        "M2H18V18" => ControllerType::M2H18v18,
+        "M2S18V20" => ControllerType::M2S18v20,
+        "M2H18V20" => ControllerType::M2H18v20,
+        _ => return Err(format!("unknown controller type {name}")),
+    };
+    Ok(controller_type)
+}
+
+fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+    let running = Arc::new(AtomicBool::new(true));
+    let handler_running = running.clone();
+    ctrlc::set_handler(move || handler_running.store(false, Ordering::SeqCst))?;
+
+    // Coast if we bail out early on an error too, not only after Ctrl-C
+    let mut builder = DeviceBuilder::new().coast_on_drop(true);
+    if let Some(reference_mv) = args.reference_mv {
+        builder = builder.reference_mv(reference_mv);
+    }
+    let mut device = builder.build_linux(args.controller, args.device, args.address)?;
+    device.reinitialise()?;
+    device.set_max_acceleration(args.motor, args.accel)?;
+    device.set_max_deceleration(args.motor, args.accel)?;
+
+    let mut target = 1.0;
+    let mut last_switch = Instant::now();
+    let mut last_print = Instant::now();
+    while running.load(Ordering::SeqCst) {
+        if last_switch.elapsed() >= Duration::from_secs(3) {
+            target = if target == 0.0 { 1.0 } else { 0.0 };
+            last_switch = Instant::now();
+        }
+        device.set_speed(args.motor, target)?;
+
+        if last_print.elapsed() >= Duration::from_millis(250) {
+            if args.controller.is_current_sensing() {
+                let current = device.read_current(args.motor)?;
+                println!("Target speed: {target}, current: {current} mA");
+            } else {
+                println!("Target speed: {target}");
+            }
+            last_print = Instant::now();
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+
+    device.close()?;
+    Ok(())
+}
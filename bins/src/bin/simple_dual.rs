@@ -12,13 +12,13 @@ struct Args {
 
     /// I2C address to address this device with
     #[arg(short, long, default_value_t = 0x10)]
-    address: u16,
+    address: u8,
 }
 
 fn main() -> anyhow::Result<()> {
     let args = Args::parse();
     let mut device =
-        pololu_motoron::Device::new(ControllerType::M2T256, args.device, args.address)?;
+        pololu_motoron::Device::new_linux(ControllerType::M2T256, args.device, args.address)?;
     device.reinitialise()?;
     device.clear_latched_status_flags(ClearLatchedStatusFlags {
         reset: true,
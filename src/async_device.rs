@@ -0,0 +1,172 @@
+use crate::commands::{
+    decode_response, encode_command, BrakingMode, ClearLatchedStatusFlags, CoastNow, Command,
+    FirmwareVersion, GetFirmwareVersion, GetVariables, Reinitialise, ResetCommandTimeout,
+    SetAllSpeeds, SetProtocolOptions, SpeedMode,
+};
+use crate::{get_braking_cmd, get_raw_speeds, get_speed_cmd};
+use crate::{ControllerType, I2cError, Result};
+use alloc::vec;
+use alloc::vec::Vec;
+use embedded_hal_async::i2c::{I2c, SevenBitAddress};
+
+/// The async counterpart of [`Device`](crate::Device), for buses implementing the
+/// [`embedded_hal_async`] I2C trait, so that talking to the controller doesn't block the executor.
+/// It has the same commands as the most common methods of [`Device`](crate::Device), which behave
+/// the same way unless noted otherwise, but none of the options from
+/// [`DeviceBuilder`](crate::DeviceBuilder). This is only available with the `async` feature.
+///
+/// ```no_run
+/// use embedded_hal_async::i2c::I2c;
+/// use pololu_motoron::{AsyncDevice, ControllerType};
+///
+/// async fn drive<I: I2c>(i2c: I) -> pololu_motoron::Result {
+///     let mut device = AsyncDevice::new(ControllerType::M2T256, i2c, 0x10).await?;
+///     device.reinitialise().await?;
+///     device.set_speed(0, 1.0).await?;
+///     device.set_speed(1, -1.0).await
+/// }
+/// ```
+pub struct AsyncDevice<I> {
+    device: I,
+    address: SevenBitAddress,
+    controller_type: ControllerType,
+    cmd_crc: bool,
+    res_crc: bool,
+    i2c_general_call: bool,
+    max_speed: i16,
+}
+
+impl<I: I2c> AsyncDevice<I> {
+    /// Create a new device object on the given I2C bus. The arguments are the same as the ones in
+    /// [`Device::new`](crate::Device::new).
+    pub async fn new(
+        controller_type: ControllerType,
+        i2c: I,
+        address: SevenBitAddress,
+    ) -> Result<AsyncDevice<I>> {
+        let mut device = AsyncDevice {
+            device: i2c,
+            address,
+            controller_type,
+            cmd_crc: true,
+            res_crc: true,
+            i2c_general_call: true,
+            max_speed: 800,
+        };
+        device.write_protocol_options().await?;
+        Ok(device)
+    }
+
+    /// Same as [`Device::reinitialise`](crate::Device::reinitialise).
+    pub async fn reinitialise(&mut self) -> Result {
+        self.write_command(&Reinitialise).await?;
+        self.write_protocol_options().await?;
+        self.clear_latched_status_flags(ClearLatchedStatusFlags {
+            reset: true,
+            ..Default::default()
+        })
+        .await
+    }
+
+    /// Same as [`Device::set_command_crc`](crate::Device::set_command_crc).
+    pub async fn set_command_crc(&mut self, enabled: bool) -> Result {
+        self.cmd_crc = enabled;
+        self.write_protocol_options().await
+    }
+
+    /// Same as [`Device::set_response_crc`](crate::Device::set_response_crc).
+    pub async fn set_response_crc(&mut self, enabled: bool) -> Result {
+        self.res_crc = enabled;
+        self.write_protocol_options().await
+    }
+
+    /// Same as [`Device::set_speed`](crate::Device::set_speed).
+    pub async fn set_speed(&mut self, motor_idx: u8, speed: f32) -> Result {
+        let cmd = get_speed_cmd(
+            self.controller_type,
+            self.max_speed,
+            motor_idx,
+            speed,
+            SpeedMode::Normal,
+        )?;
+        self.write_command(&cmd).await
+    }
+
+    /// Same as [`Device::set_all_speeds`](crate::Device::set_all_speeds).
+    pub async fn set_all_speeds(&mut self, speeds: &[f32]) -> Result {
+        let cmd = SetAllSpeeds {
+            mode: SpeedMode::Normal,
+            speeds: get_raw_speeds(self.controller_type, self.max_speed, speeds)?,
+        };
+        self.write_command(&cmd).await
+    }
+
+    /// Same as [`Device::set_braking`](crate::Device::set_braking).
+    pub async fn set_braking(&mut self, motor_idx: u8, amount: f32) -> Result {
+        let cmd = get_braking_cmd(self.controller_type, motor_idx, amount, BrakingMode::Normal)?;
+        self.write_command(&cmd).await
+    }
+
+    /// Same as [`Device::coast_now`](crate::Device::coast_now).
+    pub async fn coast_now(&mut self) -> Result {
+        self.write_command(&CoastNow).await
+    }
+
+    /// Same as [`Device::reset_command_timeout`](crate::Device::reset_command_timeout).
+    pub async fn reset_command_timeout(&mut self) -> Result {
+        self.write_command(&ResetCommandTimeout).await
+    }
+
+    /// Same as [`Device::clear_latched_status_flags`](crate::Device::clear_latched_status_flags).
+    pub async fn clear_latched_status_flags(&mut self, flags: ClearLatchedStatusFlags) -> Result {
+        self.write_command(&flags).await
+    }
+
+    /// Same as [`Device::firmware_version`](crate::Device::firmware_version).
+    pub async fn firmware_version(&mut self) -> Result<FirmwareVersion> {
+        let cmd = GetFirmwareVersion;
+        self.write_command(&cmd).await?;
+        self.read_command(&cmd).await
+    }
+
+    /// Same as [`Device::get_variables`](crate::Device::get_variables).
+    pub async fn get_variables(&mut self, motor: u8, offset: u8, length: u8) -> Result<Vec<u8>> {
+        let cmd = GetVariables {
+            motor,
+            offset,
+            length,
+        };
+        self.write_command(&cmd).await?;
+        self.read_command(&cmd).await
+    }
+
+    async fn write_protocol_options(&mut self) -> Result {
+        let cmd = SetProtocolOptions {
+            crc_for_commands: self.cmd_crc,
+            crc_for_responses: self.res_crc,
+            i2c_general_call: self.i2c_general_call,
+        };
+        self.write_command(&cmd).await
+    }
+
+    async fn write_command<C: Command>(&mut self, cmd: &C) -> Result {
+        let data = encode_command(cmd, self.cmd_crc)?;
+        log::trace!("Writing command: {data:?}");
+        self.device
+            .write(self.address, &data[..])
+            .await
+            .map_err(I2cError::new)?;
+        Ok(())
+    }
+
+    async fn read_command<C: Command>(&mut self, cmd: &C) -> Result<C::Response> {
+        let response_len = cmd.expected_response_bytes() + if self.res_crc { 1 } else { 0 };
+        let mut data = vec![0; response_len];
+        self.device
+            .read(self.address, &mut data[..])
+            .await
+            .map_err(I2cError::new)?;
+        log::trace!("Read response: {data:?}");
+        Ok(decode_response::<C>(data, self.res_crc)?)
+    }
+}
@@ -0,0 +1,324 @@
+use crate::{check_address, ControllerType, Device, Result, SupervisedDevice};
+#[cfg(feature = "linux")]
+use crate::{I2cError, LinuxI2c};
+use embedded_hal::i2c::{I2c, SevenBitAddress};
+#[cfg(feature = "linux")]
+use std::path::Path;
+use std::time::Duration;
+
+/// Lets you configure a [`Device`] before opening it. If you're happy with the defaults, you can
+/// just use [`Device::new`] instead, which is equivalent to `DeviceBuilder::new().build(...)`.
+///
+/// ```no_run
+/// use pololu_motoron::{ControllerType, DeviceBuilder};
+/// use std::time::Duration;
+///
+/// # fn main() -> anyhow::Result<()> {
+/// let mut device = DeviceBuilder::new()
+///     .response_delay(Duration::from_micros(300))
+///     .build_linux(ControllerType::M2T256, "/dev/i2c-0", 0x10)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct DeviceBuilder {
+    cmd_crc: bool,
+    res_crc: bool,
+    i2c_general_call: bool,
+    response_delay: Duration,
+    reset_delay: Duration,
+    crc_mismatch_retries: u8,
+    retry_empty_reads: bool,
+    reject_duplicate_motors: bool,
+    min_command_interval: Duration,
+    max_write_len: Option<usize>,
+    guard_reset_latch: bool,
+    coast_on_drop: bool,
+    write_options_on_open: bool,
+    reference_mv: Option<u16>,
+}
+
+impl Default for DeviceBuilder {
+    fn default() -> DeviceBuilder {
+        DeviceBuilder {
+            cmd_crc: true,
+            res_crc: true,
+            i2c_general_call: true,
+            response_delay: Duration::ZERO,
+            reset_delay: Duration::from_millis(10),
+            crc_mismatch_retries: 0,
+            retry_empty_reads: false,
+            reject_duplicate_motors: false,
+            min_command_interval: Duration::ZERO,
+            max_write_len: None,
+            guard_reset_latch: false,
+            coast_on_drop: false,
+            write_options_on_open: true,
+            reference_mv: None,
+        }
+    }
+}
+
+impl DeviceBuilder {
+    /// Creates a builder with all the default options.
+    pub fn new() -> DeviceBuilder {
+        DeviceBuilder::default()
+    }
+
+    /// Sets whether to use CRC checks, both for commands and responses. Defaults to true. See
+    /// [`DeviceBuilder::command_crc`] and [`DeviceBuilder::response_crc`] to set them separately.
+    pub fn crc(self, enabled: bool) -> DeviceBuilder {
+        self.command_crc(enabled).response_crc(enabled)
+    }
+
+    /// Sets whether commands sent to the device include a CRC byte. Defaults to true.
+    pub fn command_crc(mut self, enabled: bool) -> DeviceBuilder {
+        self.cmd_crc = enabled;
+        self
+    }
+
+    /// Sets whether responses from the device include a CRC byte, which is then checked. Defaults
+    /// to true.
+    pub fn response_crc(mut self, enabled: bool) -> DeviceBuilder {
+        self.res_crc = enabled;
+        self
+    }
+
+    /// Sets whether the device listens to the I2C general call address (0). Defaults to true.
+    pub fn i2c_general_call(mut self, enabled: bool) -> DeviceBuilder {
+        self.i2c_general_call = enabled;
+        self
+    }
+
+    /// Sets how long to wait between sending a command that has a response and reading that
+    /// response back. Some USB-I2C adapters return stale or partial data if the read comes too
+    /// soon after the write, and a delay of a few hundred microseconds usually fixes that.
+    /// Defaults to zero, so fast buses aren't slowed down.
+    pub fn response_delay(mut self, delay: Duration) -> DeviceBuilder {
+        self.response_delay = delay;
+        self
+    }
+
+    /// Sets how long [`Device::reset`] waits for the device to boot back up before talking to it
+    /// again. Some boards take longer than usual to come back up, and commands sent before they
+    /// do are lost. Defaults to 10 ms.
+    pub fn reset_delay(mut self, delay: Duration) -> DeviceBuilder {
+        self.reset_delay = delay;
+        self
+    }
+
+    /// Sets how many times to ask again for a response that fails its CRC check before giving up.
+    /// A single corrupted read is often transient on a noisy bus, so this turns occasional glitches
+    /// into invisible retries rather than errors. The device only sends a response once, so each
+    /// retry sends the command again before reading. If all retries fail, reads return
+    /// [`Error::CrcRetriesExhausted`](crate::Error::CrcRetriesExhausted). Defaults to zero (no
+    /// retries).
+    pub fn crc_mismatch_retry(mut self, count: u8) -> DeviceBuilder {
+        self.crc_mismatch_retries = count;
+        self
+    }
+
+    /// Sets whether to ask for a response once more if the read comes back empty. Some USB-I2C
+    /// bridges occasionally return no data at all when polling variables, which would otherwise
+    /// fail with a confusing CRC error. A response is considered empty if every byte of it reads
+    /// as 0xFF, which can't happen with response CRCs enabled, so this is best used with them. If
+    /// the retry is empty too, reads return
+    /// [`CommandsError::InvalidResponseLength`](crate::CommandsError::InvalidResponseLength).
+    /// Defaults to false.
+    pub fn retry_empty_reads(mut self, enabled: bool) -> DeviceBuilder {
+        self.retry_empty_reads = enabled;
+        self
+    }
+
+    /// By default, if [`Device::set_multi_speed`] is given the same motor index more than once,
+    /// only the last speed for it is sent. Setting this to true makes it return
+    /// [`Error::DuplicateMotorIndex`](crate::Error::DuplicateMotorIndex) instead, which is useful
+    /// to catch bugs when building speed lists programmatically.
+    pub fn reject_duplicate_motors(mut self, reject: bool) -> DeviceBuilder {
+        self.reject_duplicate_motors = reject;
+        self
+    }
+
+    /// Sets the minimum time between two speed commands. If a speed-setting function is called
+    /// sooner than this after the previous one, it sleeps for the rest of the interval before
+    /// sending anything, which protects fragile buses (and the controller) from being flooded by
+    /// a tight control loop. Note that this adds latency to the commands that get delayed.
+    /// Defaults to zero (no limit).
+    pub fn min_command_interval(mut self, interval: Duration) -> DeviceBuilder {
+        self.min_command_interval = interval;
+        self
+    }
+
+    /// Sets the longest write, in bytes, the bus can do in a single transaction. Some I2C
+    /// controllers (such as SMBus-only adapters, which are limited to 32 bytes) can't write long
+    /// commands like [`Device::multi_device_write`] in one go, and may silently truncate them.
+    /// With this set, commands that are too long return
+    /// [`Error::CommandTooLong`](crate::Error::CommandTooLong) instead. The I2C traits don't tell
+    /// us what the bus supports, so this defaults to no limit.
+    pub fn max_write_len(mut self, len: usize) -> DeviceBuilder {
+        self.max_write_len = Some(len);
+        self
+    }
+
+    /// While the reset flag is latched (which it is after the controller powers up), the controller
+    /// silently ignores speed commands, which usually shows up as motors that won't move for no
+    /// apparent reason. Setting this to true makes the first speed command check the flag and
+    /// return [`Error::ResetLatchActive`](crate::Error::ResetLatchActive) if it's set, instead of
+    /// having the command dropped. Once the check passes, it isn't repeated. Defaults to false, to
+    /// avoid the extra read.
+    pub fn guard_reset_latch(mut self, guard: bool) -> DeviceBuilder {
+        self.guard_reset_latch = guard;
+        self
+    }
+
+    /// Sets whether dropping the [`Device`] coasts the motors. Otherwise, if your program panics or
+    /// exits without calling [`Device::close`], the motors keep their last speed until the command
+    /// timeout stops them. This is best effort, as errors can't be reported from a drop. Defaults
+    /// to false, so that the command timeout stays in charge.
+    pub fn coast_on_drop(mut self, coast: bool) -> DeviceBuilder {
+        self.coast_on_drop = coast;
+        self
+    }
+
+    /// Sets whether to write the protocol options (CRC and general call settings) to the device
+    /// when opening it. Either way, the options set on this builder are the ones assumed from then
+    /// on. If disabled, opening the device doesn't touch the bus at all. This is useful if the
+    /// device might not be ready yet, or if you want to probe it before committing to any options.
+    /// The options are still written by anything that changes them, such as
+    /// [`Device::enable_crc`]. Defaults to true.
+    pub fn write_options_on_open(mut self, write: bool) -> DeviceBuilder {
+        self.write_options_on_open = write;
+        self
+    }
+
+    /// Sets the logic (reference) voltage of the controller, in millivolts, which is needed to
+    /// scale current and input voltage readings (see [`Device::read_current`] and
+    /// [`Device::read_input_voltage`]). Defaults to the usual one for the controller type, as
+    /// returned by [`ControllerType::default_reference_mv`].
+    pub fn reference_mv(mut self, reference_mv: u16) -> DeviceBuilder {
+        self.reference_mv = Some(reference_mv);
+        self
+    }
+
+    /// Opens the device on a Linux I2C bus with the configured options. The arguments are the same
+    /// as the ones in [`Device::new_linux`].
+    #[cfg(feature = "linux")]
+    pub fn build_linux<P: AsRef<Path>>(
+        self,
+        controller_type: ControllerType,
+        device: P,
+        address: SevenBitAddress,
+    ) -> Result<Device<LinuxI2c>> {
+        check_address(address)?;
+        let i2c = LinuxI2c::new(device, address).map_err(I2cError::new)?;
+        self.build(controller_type, i2c, address)
+    }
+
+    /// Opens the device on the given I2C bus with the configured options. The arguments are the
+    /// same as the ones in [`Device::new`]. Motoron controllers only support 7-bit I2C addresses,
+    /// so addresses above 0x7F return [`Error::InvalidAddress`](crate::Error::InvalidAddress).
+    pub fn build<I: I2c>(
+        self,
+        controller_type: ControllerType,
+        i2c: I,
+        address: SevenBitAddress,
+    ) -> Result<Device<I>> {
+        check_address(address)?;
+        let mut device = Device {
+            device: i2c,
+            address,
+            controller_type,
+            cmd_crc: self.cmd_crc,
+            res_crc: self.res_crc,
+            i2c_general_call: self.i2c_general_call,
+            response_delay: self.response_delay,
+            reset_delay: self.reset_delay,
+            crc_mismatch_retries: self.crc_mismatch_retries,
+            retry_empty_reads: self.retry_empty_reads,
+            retries: 0,
+            error_checking: false,
+            reject_duplicate_motors: self.reject_duplicate_motors,
+            min_command_interval: self.min_command_interval,
+            max_write_len: self.max_write_len,
+            guard_reset_latch: self.guard_reset_latch,
+            coast_on_drop: self.coast_on_drop,
+            reference_mv: self
+                .reference_mv
+                .unwrap_or_else(|| controller_type.default_reference_mv()),
+            reset_latch_checked: false,
+            last_speed_command: None,
+            known_speeds: None,
+            max_speed: 800,
+            max_rpm: vec![None; usize::from(controller_type.motor_channels())],
+            inverted: vec![false; usize::from(controller_type.motor_channels())],
+            firmware_version: None,
+            last_response: None,
+            recording: None,
+        };
+        if self.write_options_on_open {
+            device.write_protocol_options()?;
+        }
+        Ok(device)
+    }
+
+    /// Opens the device on the given I2C bus with the configured options, and hands it over to a
+    /// background thread that keeps the motors alive and coasts them if the bus degrades. This is
+    /// the same as calling [`DeviceBuilder::build`] followed by [`Device::supervise`], see the
+    /// latter for how the supervisor works and how failures are reported.
+    pub fn supervised<I: I2c + Send + 'static>(
+        self,
+        controller_type: ControllerType,
+        i2c: I,
+        address: SevenBitAddress,
+        keepalive_interval: Duration,
+        max_consecutive_errors: u32,
+    ) -> Result<SupervisedDevice<I>> {
+        let device = self.build(controller_type, i2c, address)?;
+        Ok(device.supervise(keepalive_interval, max_consecutive_errors))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockI2c;
+    use crate::Error;
+
+    #[test]
+    fn rejects_address_above_7_bits() {
+        let mock = MockI2c::new();
+        let result = DeviceBuilder::new().build(ControllerType::M2T256, mock.clone(), 0x80);
+        assert!(matches!(result, Err(Error::InvalidAddress(0x80))));
+        assert!(mock.writes().is_empty());
+    }
+
+    #[test]
+    fn build_writes_options_once() -> Result {
+        let mock = MockI2c::new();
+        DeviceBuilder::new()
+            .crc(false)
+            .i2c_general_call(false)
+            .build(ControllerType::M2T256, mock.clone(), 0x10)?;
+        assert_eq!(mock.writes(), [vec![0x8B, 0x00, 0x7F]]);
+        Ok(())
+    }
+
+    #[test]
+    fn supervised_reports_failures() -> Result {
+        let mock = MockI2c::new();
+        let supervised = DeviceBuilder::new().supervised(
+            ControllerType::M2T256,
+            mock.clone(),
+            0x10,
+            Duration::from_millis(1),
+            2,
+        )?;
+        mock.fail_next(usize::MAX);
+        let failure = supervised
+            .failures()
+            .recv_timeout(Duration::from_secs(1))
+            .expect("supervisor should give up");
+        assert!(matches!(failure, Error::I2c(_)));
+        Ok(())
+    }
+}
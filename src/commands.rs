@@ -1,8 +1,15 @@
-use std::ops::Range;
+// Without `std` there is no `Device`, so only the commands used by `AsyncDevice` and the public
+// encoding helpers are reachable.
+#![cfg_attr(not(feature = "std"), allow(dead_code))]
+
+use crate::{ControllerType, ProductId};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::Range;
 
 /// This function encodes a command into a byte vector that can be sent back over the wire to the
 /// pololu motoron device.
-pub fn encode_command<C: Command>(cmd: &C, with_crc: bool) -> Result<Vec<u8>> {
+pub fn encode_command<C: Command + ?Sized>(cmd: &C, with_crc: bool) -> Result<Vec<u8>> {
     let len = 1 + cmd.num_bytes() + if with_crc { 1 } else { 0 };
     let mut response = vec![0; len];
     response[0] = cmd.code();
@@ -51,7 +58,7 @@ pub enum Error {
     InvalidResponseCrc { expected: u8, actual: u8 },
 }
 
-pub type Result<T = (), E = Error> = std::result::Result<T, E>;
+pub type Result<T = (), E = Error> = core::result::Result<T, E>;
 
 /// Any type implementing this trait represents a unique command that can be sent over i2c to a
 /// pololu motoron controller. Each command will provide an easy-to-use interface to provide the
@@ -82,7 +89,7 @@ pub trait Response: Sized {
 
 impl Response for () {
     fn parse(data: Vec<u8>) -> Result<()> {
-        if data.len() != 0 {
+        if !data.is_empty() {
             Err(Error::InvalidResponseLength {
                 expected: 0,
                 actual: data.len(),
@@ -138,7 +145,7 @@ macro_rules! check_value {
 }
 macro_rules! check_value_expr {
     ($expr:ident, $min:literal, $max:literal, $field_name:literal $(,)?) => {
-        if $expr < $min || $expr > $max {
+        if !($min..=$max).contains(&$expr) {
             return Err(Error::InvalidValue {
                 min: $min,
                 max: $max,
@@ -162,6 +169,7 @@ impl Command for GetFirmwareVersion {
 
 /// The firmware version information provided by the chip
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FirmwareVersion {
     /// Product ID, as per the table in [this page](https://www.pololu.com/docs/0J84/9#cmd-get-firmware-version)
     pub product_id: u16,
@@ -169,6 +177,20 @@ pub struct FirmwareVersion {
     pub major_fw_version: u8,
 }
 
+impl FirmwareVersion {
+    /// Returns the controller this firmware runs on, or `None` if the product ID isn't a known one.
+    /// See [`ControllerType::from_product_id`] for how controllers sharing a product ID are
+    /// handled.
+    pub fn controller_type(&self) -> Option<ControllerType> {
+        self.product().controller_type()
+    }
+
+    /// Returns the product ID as a [`ProductId`], which is easier to interpret than the raw number.
+    pub fn product(&self) -> ProductId {
+        self.product_id.into()
+    }
+}
+
 impl Response for FirmwareVersion {
     fn parse(data: Vec<u8>) -> Result<FirmwareVersion> {
         if data.len() != 4 {
@@ -236,8 +258,8 @@ impl Command for WriteEeprom {
     fn encode_body(&self, bytes: &mut [u8]) -> Result<()> {
         check_value!(self, offset, 0, 0x7F);
         bytes[0] = self.offset;
-        bytes[1] = u8::from((self.value & 0x80) != 0);
-        bytes[2] = self.value & 0x7F;
+        bytes[1] = self.value & 0x7F;
+        bytes[2] = u8::from((self.value & 0x80) != 0);
         write_inverted_bytes(bytes, 0..3, 3);
         Ok(())
     }
@@ -297,12 +319,7 @@ impl Command for SetVariable {
         check_value!(self, value, 0, 0x3FFF);
         bytes[0] = self.motor;
         bytes[1] = self.offset;
-        bytes[2] = (self.value & 0x7F)
-            .try_into()
-            .expect("could not convert u16 to u8 with mask");
-        bytes[3] = ((self.value >> 7) & 0x7F)
-            .try_into()
-            .expect("could not convert u16 to u8 with mask");
+        write_7bit_pair(bytes, 2, self.value);
         Ok(())
     }
 }
@@ -361,33 +378,39 @@ impl Command for ClearLatchedStatusFlags {
             | (u16::from(self.motor_fault_latched) << 3)
             | (u16::from(self.command_timeout_latched) << 2)
             | (u16::from(self.crc_error) << 1)
-            | (u16::from(self.protocol_error) << 0);
+            | u16::from(self.protocol_error);
 
-        bytes[0] = (flags & 0x7F)
-            .try_into()
-            .expect("could not convert u16 to u8 with mask");
-        bytes[1] = ((flags >> 7) & 0x7)
-            .try_into()
-            .expect("could not convert u16 to u8 with mask");
+        write_7bit_pair(bytes, 0, flags & 0x3FF);
         Ok(())
     }
 }
 
+/// The latched status flags to set with
+/// [`Device::set_latched_status_flags`](crate::Device::set_latched_status_flags). Only the latched
+/// flags can be set, as the rest reflect the controller's current state.
+#[derive(Default)]
 pub struct SetLatchedStatusFlags {
-    pub flags: u16,
+    pub protocol_error: bool,
+    pub crc_error: bool,
+    pub command_timeout_latched: bool,
+    pub motor_fault_latched: bool,
+    pub no_power_latched: bool,
+    pub uart_error: bool,
+    pub reset: bool,
 }
 impl Command for SetLatchedStatusFlags {
     type Response = ();
     plain_code!(0xAC);
     plain_byte_count!(2);
     fn encode_body(&self, bytes: &mut [u8]) -> Result<()> {
-        check_value!(self, flags, 0, 0x3FF);
-        bytes[0] = (self.flags & 0x7F)
-            .try_into()
-            .expect("could not convert u16 to u8 with mask");
-        bytes[1] = ((self.flags >> 7) & 0x7)
-            .try_into()
-            .expect("could not convert u16 to u8 with mask");
+        let flags: u16 = (u16::from(self.reset) << 9)
+            | (u16::from(self.uart_error) << 5)
+            | (u16::from(self.no_power_latched) << 4)
+            | (u16::from(self.motor_fault_latched) << 3)
+            | (u16::from(self.command_timeout_latched) << 2)
+            | (u16::from(self.crc_error) << 1)
+            | u16::from(self.protocol_error);
+        write_7bit_pair(bytes, 0, flags);
         Ok(())
     }
 }
@@ -416,17 +439,10 @@ impl Command for SetSpeed {
     fn encode_body(&self, bytes: &mut [u8]) -> Result<()> {
         check_value!(self, motor, 0, 3);
         check_value!(self, speed, -800, 800);
-        // SAFETY: we assume this system uses a 2's compliment representation of signed integers.
-        // Regardless, an i16 can be safely interpreted as a u16 as all possible 16-bit
-        // representations are valid in both.
-        let speed_as_2c: u16 = unsafe { std::mem::transmute(self.speed) };
+        // Signed integers are always 2's complement, so this keeps the bits as they are
+        let speed_as_2c = self.speed as u16;
         bytes[0] = self.motor;
-        bytes[1] = (speed_as_2c & 0x7F)
-            .try_into()
-            .expect("could not convert u16 to u8 with mask");
-        bytes[2] = ((speed_as_2c >> 7) & 0x7F)
-            .try_into()
-            .expect("could not convert u16 to u8 with mask");
+        write_7bit_pair(bytes, 1, speed_as_2c);
         Ok(())
     }
 }
@@ -451,16 +467,9 @@ impl Command for SetAllSpeeds {
         for (idx, speed) in self.speeds.iter().enumerate() {
             let speed = *speed;
             check_value_expr!(speed, -800, 800, "speeds");
-            // SAFETY: we assume this CPU uses a 2's compliment representation of signed integers.
-            // Regardless, an i16 can be safely interpreted as a u16 as all possible 16-bit
-            // representations are valid in both.
-            let speed_as_2c: u16 = unsafe { std::mem::transmute(speed) };
-            bytes[idx * 2] = (speed_as_2c & 0x7F)
-                .try_into()
-                .expect("could not convert u16 to u8 with mask");
-            bytes[idx * 2 + 1] = ((speed_as_2c >> 7) & 0x7F)
-                .try_into()
-                .expect("could not convert u16 to u8 with mask");
+            // Signed integers are always 2's complement, so this keeps the bits as they are
+            let speed_as_2c = speed as u16;
+            write_7bit_pair(bytes, idx * 2, speed_as_2c);
         }
         Ok(())
     }
@@ -494,7 +503,7 @@ pub enum BrakingMode {
 pub struct SetBraking {
     pub mode: BrakingMode,
     pub motor: u8,
-    pub ammount: u16,
+    pub amount: u16,
 }
 impl Command for SetBraking {
     type Response = ();
@@ -507,14 +516,9 @@ impl Command for SetBraking {
     plain_byte_count!(3);
     fn encode_body(&self, bytes: &mut [u8]) -> Result<()> {
         check_value!(self, motor, 1, 3);
-        check_value!(self, ammount, 0, 800);
+        check_value!(self, amount, 0, 800);
         bytes[0] = self.motor;
-        bytes[1] = (self.ammount & 0x7F)
-            .try_into()
-            .expect("could not convert u16 to u8 with mask");
-        bytes[2] = ((self.ammount >> 7) & 0x7F)
-            .try_into()
-            .expect("could not convert u16 to u8 with mask");
+        write_7bit_pair(bytes, 1, self.amount);
         Ok(())
     }
 }
@@ -532,8 +536,8 @@ pub struct MultiDeviceErrorCheck {
     pub device_count: u8,
 }
 impl Command for MultiDeviceErrorCheck {
-    type Response = MultiDeviceErrorCheckReponse;
-    plain_code!(0xF5);
+    type Response = MultiDeviceErrorCheckResponse;
+    plain_code!(0xF9);
     plain_byte_count!(2);
     fn encode_body(&self, bytes: &mut [u8]) -> Result<()> {
         check_value!(self, starting_device_number, 0, 0x7F);
@@ -547,17 +551,36 @@ impl Command for MultiDeviceErrorCheck {
     }
 }
 
+/// The response to a multi-device error check. The protocol only defines two values for it, the
+/// acknowledgement byte `0x3C` sent when no device in the range has an error, and `0x00`, which is
+/// what gets read when a device with an active error stays silent instead of acknowledging.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub enum MultiDeviceErrorCheckReponse {
-    // 0x00
+pub enum MultiDeviceErrorCheckResponse {
+    /// At least one of the devices checked has an error active (`0x00`).
     ErrorActive,
-    // 0x3C
+    /// None of the devices checked have an error active (`0x3C`).
     Ok,
+    /// Any other byte, which the protocol doesn't define. This usually points to a problem on the
+    /// bus, rather than with the devices.
     Unknown(u8),
 }
 
-impl Response for MultiDeviceErrorCheckReponse {
-    fn parse(data: Vec<u8>) -> Result<MultiDeviceErrorCheckReponse> {
+impl MultiDeviceErrorCheckResponse {
+    /// Returns true if none of the devices checked reported an error.
+    pub fn is_ok(&self) -> bool {
+        matches!(self, MultiDeviceErrorCheckResponse::Ok)
+    }
+
+    /// Returns true if at least one of the devices checked reported an error. Note that an
+    /// [`MultiDeviceErrorCheckResponse::Unknown`] response is neither ok nor an error, as we can't
+    /// tell what happened.
+    pub fn is_error(&self) -> bool {
+        matches!(self, MultiDeviceErrorCheckResponse::ErrorActive)
+    }
+}
+
+impl Response for MultiDeviceErrorCheckResponse {
+    fn parse(data: Vec<u8>) -> Result<MultiDeviceErrorCheckResponse> {
         if data.len() != 1 {
             Err(Error::InvalidResponseLength {
                 expected: 1,
@@ -565,14 +588,15 @@ impl Response for MultiDeviceErrorCheckReponse {
             })
         } else {
             Ok(match data[0] {
-                0x00 => MultiDeviceErrorCheckReponse::ErrorActive,
-                0x3C => MultiDeviceErrorCheckReponse::Ok,
-                v => MultiDeviceErrorCheckReponse::Unknown(v),
+                0x00 => MultiDeviceErrorCheckResponse::ErrorActive,
+                0x3C => MultiDeviceErrorCheckResponse::Ok,
+                v => MultiDeviceErrorCheckResponse::Unknown(v),
             })
         }
     }
 }
 
+/// Sends the same command to a range of devices, each of which gets its own copy of the body.
 pub struct MultiDeviceWrite<C: Command> {
     pub starting_device_number: u8,
     pub device_count: u8,
@@ -580,28 +604,30 @@ pub struct MultiDeviceWrite<C: Command> {
 }
 impl<C: Command> Command for MultiDeviceWrite<C> {
     type Response = ();
-    plain_code!(0xF9);
+    plain_code!(0xFA);
     fn num_bytes(&self) -> usize {
-        4 + self.command.num_bytes()
+        4 + usize::from(self.device_count) * self.command.num_bytes()
     }
     fn encode_body(&self, bytes: &mut [u8]) -> Result<()> {
         check_value!(self, starting_device_number, 0, 0x7F);
         check_value!(self, device_count, 0, 0x7F);
         let command_length = self.command.num_bytes();
-        let code = self.command.code();
 
         bytes[0] = self.starting_device_number;
         bytes[1] = self.device_count;
         bytes[2] = command_length
             .try_into()
             .expect("command length guaranteed to be under 0x7F");
-        bytes[3] = code;
-        self.command.encode_body(&mut bytes[4..])?;
+        // Everything after the command byte has to be a data byte, including the command itself
+        bytes[3] = self.command.code() & 0x7F;
+        for device_bytes in bytes[4..].chunks_exact_mut(command_length.max(1)) {
+            self.command.encode_body(device_bytes)?;
+        }
         Ok(())
     }
 }
 
-fn get_crc(message: &[u8]) -> u8 {
+pub(crate) fn get_crc(message: &[u8]) -> u8 {
     let mut crc = 0;
     // for (uint8_t i = 0; i < length; i++)
     for byte in message {
@@ -616,6 +642,13 @@ fn get_crc(message: &[u8]) -> u8 {
     crc
 }
 
+/// Writes a value into two bytes starting at `offset`, as the protocol expects most multi-byte
+/// values: the lowest 7 bits first, followed by the next 7 bits. Any higher bits are dropped.
+fn write_7bit_pair(bytes: &mut [u8], offset: usize, value: u16) {
+    bytes[offset] = (value & 0x7F) as u8;
+    bytes[offset + 1] = ((value >> 7) & 0x7F) as u8;
+}
+
 fn write_inverted_bytes(data: &mut [u8], orig: Range<usize>, write_offset: usize) {
     if write_offset + orig.len() > data.len() {
         panic!("not enough bytes in data to do an invert of the length required.");
@@ -625,3 +658,330 @@ fn write_inverted_bytes(data: &mut [u8], orig: Range<usize>, write_offset: usize
         data[i + write_offset] = data[i] ^ 0x7F;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn multi_device_error_check_responses() {
+        let ok = MultiDeviceErrorCheckResponse::parse(vec![0x3C]).unwrap();
+        assert_eq!(ok, MultiDeviceErrorCheckResponse::Ok);
+        assert!(ok.is_ok());
+        assert!(!ok.is_error());
+
+        let error = MultiDeviceErrorCheckResponse::parse(vec![0x00]).unwrap();
+        assert_eq!(error, MultiDeviceErrorCheckResponse::ErrorActive);
+        assert!(!error.is_ok());
+        assert!(error.is_error());
+
+        let unknown = MultiDeviceErrorCheckResponse::parse(vec![0x12]).unwrap();
+        assert_eq!(unknown, MultiDeviceErrorCheckResponse::Unknown(0x12));
+        assert!(!unknown.is_ok());
+        assert!(!unknown.is_error());
+
+        assert!(MultiDeviceErrorCheckResponse::parse(vec![]).is_err());
+    }
+
+    #[test]
+    fn write_eeprom_encoding() {
+        // The value is split into its low 7 bits and its MSB, followed by the inverted body
+        let cmd = WriteEeprom {
+            offset: 0x01,
+            value: 0x85,
+        };
+        assert_eq!(
+            encode_command(&cmd, false).unwrap(),
+            [0x95, 0x01, 0x05, 0x01, 0x7E, 0x7A, 0x7E]
+        );
+        let cmd = WriteEeprom {
+            offset: 0x02,
+            value: 0x7F,
+        };
+        assert_eq!(
+            encode_command(&cmd, false).unwrap(),
+            [0x95, 0x02, 0x7F, 0x00, 0x7D, 0x00, 0x7F]
+        );
+    }
+
+    #[test]
+    fn clear_motor_fault_encoding() {
+        let cmd = ClearMotorFault {
+            unconditional: true,
+        };
+        assert_eq!(encode_command(&cmd, false).unwrap(), [0xA6, 0x01]);
+        let cmd = ClearMotorFault {
+            unconditional: false,
+        };
+        assert_eq!(encode_command(&cmd, false).unwrap(), [0xA6, 0x00]);
+    }
+
+    #[test]
+    fn command_codes() {
+        fn code<C: Command>(cmd: C) -> u8 {
+            cmd.code()
+        }
+        let speed = |mode| SetSpeed {
+            mode,
+            motor: 1,
+            speed: 0,
+        };
+        let all_speeds = |mode| SetAllSpeeds {
+            mode,
+            speeds: vec![0, 0],
+        };
+        let braking = |mode| SetBraking {
+            mode,
+            motor: 1,
+            amount: 0,
+        };
+        let codes = [
+            (code(GetFirmwareVersion), 0x87),
+            (
+                code(SetProtocolOptions {
+                    crc_for_commands: true,
+                    crc_for_responses: true,
+                    i2c_general_call: true,
+                }),
+                0x8B,
+            ),
+            (
+                code(ReadEeprom {
+                    offset: 0,
+                    length: 1,
+                }),
+                0x93,
+            ),
+            (
+                code(WriteEeprom {
+                    offset: 0,
+                    value: 0,
+                }),
+                0x95,
+            ),
+            (code(Reinitialise), 0x96),
+            (code(Reset), 0x99),
+            (
+                code(GetVariables {
+                    motor: 0,
+                    offset: 0,
+                    length: 1,
+                }),
+                0x9A,
+            ),
+            (
+                code(SetVariable {
+                    motor: 0,
+                    offset: 0,
+                    value: 0,
+                }),
+                0x9C,
+            ),
+            (code(CoastNow), 0xA5),
+            (
+                code(ClearMotorFault {
+                    unconditional: false,
+                }),
+                0xA6,
+            ),
+            (code(ClearLatchedStatusFlags::default()), 0xA9),
+            (code(SetLatchedStatusFlags::default()), 0xAC),
+            (code(braking(BrakingMode::Normal)), 0xB1),
+            (code(braking(BrakingMode::Now)), 0xB2),
+            (code(speed(SpeedMode::Normal)), 0xD1),
+            (code(speed(SpeedMode::Now)), 0xD2),
+            (code(speed(SpeedMode::Buffered)), 0xD4),
+            (code(all_speeds(SpeedMode::Normal)), 0xE1),
+            (code(all_speeds(SpeedMode::Now)), 0xE2),
+            (code(all_speeds(SpeedMode::Buffered)), 0xE4),
+            (
+                code(SetAllSpeedsUsingBuffers {
+                    mode: SpeedModeNoBuffer::Normal,
+                }),
+                0xF0,
+            ),
+            (
+                code(SetAllSpeedsUsingBuffers {
+                    mode: SpeedModeNoBuffer::Now,
+                }),
+                0xF3,
+            ),
+            (code(ResetCommandTimeout), 0xF5),
+            (
+                code(MultiDeviceErrorCheck {
+                    starting_device_number: 0,
+                    device_count: 1,
+                }),
+                0xF9,
+            ),
+            (
+                code(MultiDeviceWrite {
+                    starting_device_number: 0,
+                    device_count: 1,
+                    command: CoastNow,
+                }),
+                0xFA,
+            ),
+        ];
+        for (actual, expected) in codes {
+            assert_eq!(actual, expected, "{expected:#04x}");
+        }
+    }
+
+    #[test]
+    fn set_variable_encoding() {
+        // 1000 is 0x3E8, which splits into its low 7 bits (0x68) and the next 7 (0x07)
+        let cmd = SetVariable {
+            motor: 1,
+            offset: 10,
+            value: 1000,
+        };
+        assert_eq!(
+            encode_command(&cmd, false).unwrap(),
+            [0x9C, 0x01, 0x0A, 0x68, 0x07]
+        );
+    }
+
+    #[test]
+    fn negative_speed_encoding() {
+        // -400 is 0xFE70 in 2's complement, which splits into 0x70 and 0x7C
+        let cmd = SetSpeed {
+            mode: SpeedMode::Normal,
+            motor: 1,
+            speed: -400,
+        };
+        assert_eq!(
+            encode_command(&cmd, false).unwrap(),
+            [0xD1, 0x01, 0x70, 0x7C]
+        );
+        let cmd = SetAllSpeeds {
+            mode: SpeedMode::Normal,
+            speeds: vec![-400, 400],
+        };
+        assert_eq!(
+            encode_command(&cmd, false).unwrap(),
+            [0xE1, 0x70, 0x7C, 0x10, 0x03]
+        );
+    }
+
+    #[test]
+    fn multi_device_write_encoding() {
+        let cmd = MultiDeviceWrite {
+            starting_device_number: 0x10,
+            device_count: 2,
+            command: SetSpeed {
+                mode: SpeedMode::Normal,
+                motor: 1,
+                speed: 400,
+            },
+        };
+        // The inner command byte has its top bit cleared, and its body is repeated per device
+        assert_eq!(
+            encode_command(&cmd, false).unwrap(),
+            [0xFA, 0x10, 0x02, 0x03, 0x51, 0x01, 0x10, 0x03, 0x01, 0x10, 0x03]
+        );
+    }
+
+    #[test]
+    fn multi_device_write_without_body() {
+        let cmd = MultiDeviceWrite {
+            starting_device_number: 0x20,
+            device_count: 3,
+            command: CoastNow,
+        };
+        assert_eq!(
+            encode_command(&cmd, false).unwrap(),
+            [0xFA, 0x20, 0x03, 0x00, 0x25]
+        );
+    }
+
+    #[test]
+    fn crc_of_empty_data() {
+        assert_eq!(get_crc(&[]), 0x00);
+    }
+
+    #[test]
+    fn crc_of_single_byte() {
+        assert_eq!(get_crc(&[0x00]), 0x00);
+        assert_eq!(get_crc(&[0x87]), 0x3C);
+    }
+
+    #[test]
+    fn crc_of_documented_commands() {
+        // Examples from the Motoron user's guide
+        assert_eq!(get_crc(&[0x83, 0x01]), 0x17);
+        assert_eq!(get_crc(&[0x8B, 0x04, 0x7B]), 0x43);
+    }
+
+    #[test]
+    fn command_crc() {
+        let cmd = SetProtocolOptions {
+            crc_for_commands: false,
+            crc_for_responses: false,
+            i2c_general_call: true,
+        };
+        assert_eq!(
+            encode_command(&cmd, true).unwrap(),
+            [0x8B, 0x04, 0x7B, 0x43]
+        );
+        assert_eq!(encode_command(&cmd, false).unwrap(), [0x8B, 0x04, 0x7B]);
+    }
+
+    #[test]
+    fn response_crc() {
+        let response = [0xCE, 0x00, 0x02, 0x01];
+        let mut data = response.to_vec();
+        data.push(get_crc(&response));
+        let version = decode_response::<GetFirmwareVersion>(data.clone(), true).unwrap();
+        assert_eq!(version.product_id, 0xCE);
+
+        data[4] ^= 0x01;
+        assert!(matches!(
+            decode_response::<GetFirmwareVersion>(data, true),
+            Err(Error::InvalidResponseCrc { .. })
+        ));
+    }
+
+    #[test]
+    fn seven_bit_pairs() {
+        for (value, expected) in [
+            (0, [0x00, 0x00]),
+            (0x7F, [0x7F, 0x00]),
+            (0x80, [0x00, 0x01]),
+            (0x3FFF, [0x7F, 0x7F]),
+        ] {
+            let mut bytes = [0xFF; 3];
+            write_7bit_pair(&mut bytes, 1, value);
+            assert_eq!(bytes, [0xFF, expected[0], expected[1]], "{value:#x}");
+        }
+    }
+
+    #[test]
+    fn firmware_version_controller_type() {
+        let version = FirmwareVersion::parse(vec![0xCE, 0x00, 0x02, 0x01]).unwrap();
+        assert_eq!(version.controller_type(), Some(ControllerType::M2T256));
+        let version = FirmwareVersion::parse(vec![0xD2, 0x00, 0x02, 0x01]).unwrap();
+        assert_eq!(version.controller_type(), Some(ControllerType::M2S24v14));
+        let version = FirmwareVersion::parse(vec![0x34, 0x12, 0x02, 0x01]).unwrap();
+        assert_eq!(version.controller_type(), None);
+    }
+
+    #[test]
+    fn firmware_version_product() {
+        let version = FirmwareVersion::parse(vec![0xCE, 0x00, 0x02, 0x01]).unwrap();
+        assert_eq!(version.product(), ProductId::M2T256);
+        let version = FirmwareVersion::parse(vec![0x34, 0x12, 0x02, 0x01]).unwrap();
+        assert_eq!(version.product(), ProductId::Unknown(0x1234));
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn firmware_version_json_round_trip() {
+        let version = FirmwareVersion::parse(vec![0xCE, 0x00, 0x02, 0x01]).unwrap();
+        let json = serde_json::to_string(&version).unwrap();
+        assert_eq!(
+            serde_json::from_str::<FirmwareVersion>(&json).unwrap(),
+            version
+        );
+    }
+}
@@ -1,6 +1,7 @@
 /// Reprents the controller type being worked on. If you're not sure which one you have or what
 /// capabilities it has, you can consult [this document](https://www.pololu.com/docs/0J84/1).
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ControllerType {
     M1T550,
     M1U550,
@@ -51,4 +52,508 @@ impl ControllerType {
             | ControllerType::M3H256 => 3,
         }
     }
+
+    /// Returns whether this controller can measure the current going through each motor. This is
+    /// true of the MP6550-based controllers (the `*550` models) and of the high-power controllers.
+    pub fn is_current_sensing(&self) -> bool {
+        match self {
+            ControllerType::M1T550
+            | ControllerType::M1U550
+            | ControllerType::M2T550
+            | ControllerType::M2U550
+            | ControllerType::M3S550
+            | ControllerType::M3H550
+            | ControllerType::M2S24v14
+            | ControllerType::M2H24v14
+            | ControllerType::M2S24v16
+            | ControllerType::M2H24v16
+            | ControllerType::M2S18v18
+            | ControllerType::M2H18v18
+            | ControllerType::M2S18v20
+            | ControllerType::M2H18v20 => true,
+            ControllerType::M1T256
+            | ControllerType::M1U256
+            | ControllerType::M2T256
+            | ControllerType::M2U256
+            | ControllerType::M3S256
+            | ControllerType::M3H256 => false,
+        }
+    }
+
+    /// Same as [`ControllerType::is_current_sensing`].
+    pub fn has_current_sensing(&self) -> bool {
+        self.is_current_sensing()
+    }
+
+    /// Returns the highest input (VIN) voltage this controller is rated for, in volts. Exceeding it
+    /// can permanently damage the controller, so this is handy for checking that a power supply
+    /// is safe before driving anything. The values come from Pololu's product pages.
+    pub fn max_voltage(&self) -> f32 {
+        match self {
+            ControllerType::M1T550
+            | ControllerType::M1U550
+            | ControllerType::M2T550
+            | ControllerType::M2U550
+            | ControllerType::M3S550
+            | ControllerType::M3H550 => 22.,
+            ControllerType::M1T256
+            | ControllerType::M1U256
+            | ControllerType::M2T256
+            | ControllerType::M2U256
+            | ControllerType::M3S256
+            | ControllerType::M3H256 => 48.,
+            ControllerType::M2S24v14
+            | ControllerType::M2H24v14
+            | ControllerType::M2S24v16
+            | ControllerType::M2H24v16 => 40.,
+            ControllerType::M2S18v18
+            | ControllerType::M2H18v18
+            | ControllerType::M2S18v20
+            | ControllerType::M2H18v20 => 30.,
+        }
+    }
+
+    /// Returns roughly how much current, in amps, each motor channel of this controller can
+    /// deliver continuously. This is the rating at room temperature without any extra cooling, so
+    /// treat it as a guideline rather than a hard limit. The values come from Pololu's product
+    /// pages. Every current model has a rating, so this never returns `None` yet, but a future
+    /// model without one would.
+    pub fn continuous_current_per_motor(&self) -> Option<f32> {
+        match self {
+            ControllerType::M1T550
+            | ControllerType::M1U550
+            | ControllerType::M2T550
+            | ControllerType::M2U550
+            | ControllerType::M3S550
+            | ControllerType::M3H550 => Some(1.5),
+            ControllerType::M1T256
+            | ControllerType::M1U256
+            | ControllerType::M2T256
+            | ControllerType::M2U256
+            | ControllerType::M3S256
+            | ControllerType::M3H256 => Some(2.),
+            ControllerType::M2S24v14 | ControllerType::M2H24v14 => Some(14.),
+            ControllerType::M2S24v16 | ControllerType::M2H24v16 => Some(16.),
+            ControllerType::M2S18v18 | ControllerType::M2H18v18 => Some(18.),
+            ControllerType::M2S18v20 | ControllerType::M2H18v20 => Some(20.),
+        }
+    }
+
+    /// Returns whether this controller supports a configurable per-motor current limit. The
+    /// firmware limits current based on the current sense reading, so this is true of the same
+    /// controllers as [`ControllerType::is_current_sensing`].
+    pub fn is_current_limiting(&self) -> bool {
+        self.is_current_sensing()
+    }
+
+    /// Returns whether this controller reports its own temperature. None of the current Motoron
+    /// controllers do, so this always returns false, but it lets you write code that won't need
+    /// to change if a future model does.
+    pub fn has_temperature_sensor(&self) -> bool {
+        false
+    }
+
+    /// Returns the gain of the current sensing on this controller, in amps per volt of current sense
+    /// output, or `None` if this controller can't sense current. To convert a raw current sense
+    /// reading into amps, first convert it into volts by multiplying it by the controller's logic
+    /// (reference) voltage divided by 1024, then multiply that by this scale. The values come from
+    /// the current sense output of each model: 200 mV/A for the `*550` models, 10 mV/A for the
+    /// 18v20 models, and 20 mV/A for the rest of the high-power models.
+    pub fn current_sense_scale(&self) -> Option<f32> {
+        match self {
+            ControllerType::M1T550
+            | ControllerType::M1U550
+            | ControllerType::M2T550
+            | ControllerType::M2U550
+            | ControllerType::M3S550
+            | ControllerType::M3H550 => Some(5.),
+            ControllerType::M2S18v20 | ControllerType::M2H18v20 => Some(100.),
+            ControllerType::M2S24v14
+            | ControllerType::M2H24v14
+            | ControllerType::M2S24v16
+            | ControllerType::M2H24v16
+            | ControllerType::M2S18v18
+            | ControllerType::M2H18v18 => Some(50.),
+            ControllerType::M1T256
+            | ControllerType::M1U256
+            | ControllerType::M2T256
+            | ControllerType::M2U256
+            | ControllerType::M3S256
+            | ControllerType::M3H256 => None,
+        }
+    }
+
+    /// Returns the ratio of the voltage divider this controller uses to measure its input voltage,
+    /// which is what a VIN reading, converted into volts the same way as a current sense reading
+    /// (see [`ControllerType::current_sense_scale`]), must be multiplied by to get the actual input
+    /// voltage. The values come from the divider resistors of each model.
+    pub fn vin_sense_scale(&self) -> f32 {
+        match self {
+            ControllerType::M1T550
+            | ControllerType::M1U550
+            | ControllerType::M2T550
+            | ControllerType::M2U550
+            | ControllerType::M3S550
+            | ControllerType::M3H550 => 459. / 47.,
+            ControllerType::M1T256
+            | ControllerType::M1U256
+            | ControllerType::M2T256
+            | ControllerType::M2U256
+            | ControllerType::M3S256
+            | ControllerType::M3H256
+            | ControllerType::M2S24v14
+            | ControllerType::M2H24v14
+            | ControllerType::M2S24v16
+            | ControllerType::M2H24v16
+            | ControllerType::M2S18v18
+            | ControllerType::M2H18v18
+            | ControllerType::M2S18v20
+            | ControllerType::M2H18v20 => 1047. / 47.,
+        }
+    }
+
+    /// Returns the usual logic (reference) voltage of this controller, in millivolts, which current
+    /// sense and VIN readings are relative to. The Arduino shields (the `M*S*` models) usually run
+    /// at 5 V, while the Raspberry Pi hats (the `M*H*` models) and the rest usually run at 3.3 V.
+    /// If yours doesn't, set it with
+    /// [`DeviceBuilder::reference_mv`](crate::DeviceBuilder::reference_mv).
+    pub fn default_reference_mv(&self) -> u16 {
+        match self {
+            ControllerType::M3S550
+            | ControllerType::M3S256
+            | ControllerType::M2S24v14
+            | ControllerType::M2S24v16
+            | ControllerType::M2S18v18
+            | ControllerType::M2S18v20 => 5000,
+            ControllerType::M1T550
+            | ControllerType::M1U550
+            | ControllerType::M2T550
+            | ControllerType::M2U550
+            | ControllerType::M1T256
+            | ControllerType::M1U256
+            | ControllerType::M2T256
+            | ControllerType::M2U256
+            | ControllerType::M3H550
+            | ControllerType::M3H256
+            | ControllerType::M2H24v14
+            | ControllerType::M2H24v16
+            | ControllerType::M2H18v18
+            | ControllerType::M2H18v20 => 3300,
+        }
+    }
+
+    /// Returns the product this controller reports in its firmware version (see
+    /// [`FirmwareVersion::product`](crate::FirmwareVersion::product)). Variants that only differ in
+    /// form factor, such as the [`ControllerType::M2T256`] and the [`ControllerType::M2U256`], run
+    /// the same firmware and so share a product ID.
+    pub fn product(&self) -> ProductId {
+        match self {
+            ControllerType::M3S256 | ControllerType::M3H256 => ProductId::M3S256,
+            ControllerType::M3S550 | ControllerType::M3H550 => ProductId::M3S550,
+            ControllerType::M2T256 | ControllerType::M2U256 => ProductId::M2T256,
+            ControllerType::M2T550 | ControllerType::M2U550 => ProductId::M2T550,
+            ControllerType::M1T256 | ControllerType::M1U256 => ProductId::M1T256,
+            ControllerType::M1T550 | ControllerType::M1U550 => ProductId::M1T550,
+            ControllerType::M2S24v14 | ControllerType::M2H24v14 => ProductId::M2S24v14,
+            ControllerType::M2S18v18 | ControllerType::M2H18v18 => ProductId::M2S18v18,
+            ControllerType::M2S24v16 | ControllerType::M2H24v16 => ProductId::M2S24v16,
+            ControllerType::M2S18v20 | ControllerType::M2H18v20 => ProductId::M2S18v20,
+        }
+    }
+
+    /// Returns the raw product ID this controller reports in its firmware version (see
+    /// [`FirmwareVersion::product_id`](crate::FirmwareVersion::product_id)). This is the same as
+    /// [`ControllerType::product`], as a number.
+    pub fn product_id(&self) -> u16 {
+        self.product().into()
+    }
+
+    /// Returns the controller with the given product ID, or `None` if it isn't a known one. As the
+    /// product ID doesn't tell variants that only differ in form factor apart (see
+    /// [`ControllerType::product`]), this returns the first one of them, such as the
+    /// [`ControllerType::M2T256`] rather than the [`ControllerType::M2U256`]. Compare product IDs
+    /// rather than controller types if that difference matters.
+    pub fn from_product_id(product_id: u16) -> Option<ControllerType> {
+        ProductId::from(product_id).controller_type()
+    }
+}
+
+/// The product ID a controller reports in its firmware version, which identifies the kind of
+/// controller the firmware runs on. Each variant covers the controllers that only differ in form
+/// factor, as they run the same firmware (see [`ControllerType::product`]). See the table in
+/// [this page](https://www.pololu.com/docs/0J84/9#cmd-get-firmware-version) for the full list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ProductId {
+    /// The M3S256 and M3H256.
+    M3S256,
+    /// The M3S550 and M3H550.
+    M3S550,
+    /// The M2T256 and M2U256.
+    M2T256,
+    /// The M2T550 and M2U550.
+    M2T550,
+    /// The M1T256 and M1U256.
+    M1T256,
+    /// The M1T550 and M1U550.
+    M1T550,
+    /// The M2S24v14 and M2H24v14.
+    M2S24v14,
+    /// The M2S18v18 and M2H18v18.
+    M2S18v18,
+    /// The M2S24v16 and M2H24v16.
+    M2S24v16,
+    /// The M2S18v20 and M2H18v20.
+    M2S18v20,
+    /// A product ID this crate doesn't know about, such as one from a newer controller.
+    Unknown(u16),
+}
+
+impl ProductId {
+    /// Returns the controller with this product ID, or `None` if it's unknown. See
+    /// [`ControllerType::from_product_id`] for how controllers sharing a product ID are handled.
+    pub fn controller_type(&self) -> Option<ControllerType> {
+        match self {
+            ProductId::M3S256 => Some(ControllerType::M3S256),
+            ProductId::M3S550 => Some(ControllerType::M3S550),
+            ProductId::M2T256 => Some(ControllerType::M2T256),
+            ProductId::M2T550 => Some(ControllerType::M2T550),
+            ProductId::M1T256 => Some(ControllerType::M1T256),
+            ProductId::M1T550 => Some(ControllerType::M1T550),
+            ProductId::M2S24v14 => Some(ControllerType::M2S24v14),
+            ProductId::M2S18v18 => Some(ControllerType::M2S18v18),
+            ProductId::M2S24v16 => Some(ControllerType::M2S24v16),
+            ProductId::M2S18v20 => Some(ControllerType::M2S18v20),
+            ProductId::Unknown(_) => None,
+        }
+    }
+}
+
+impl From<u16> for ProductId {
+    fn from(product_id: u16) -> ProductId {
+        match product_id {
+            0x00CC => ProductId::M3S256,
+            0x00CD => ProductId::M3S550,
+            0x00CE => ProductId::M2T256,
+            0x00CF => ProductId::M2T550,
+            0x00D0 => ProductId::M1T256,
+            0x00D1 => ProductId::M1T550,
+            0x00D2 => ProductId::M2S24v14,
+            0x00D3 => ProductId::M2S18v18,
+            0x00D4 => ProductId::M2S24v16,
+            0x00D5 => ProductId::M2S18v20,
+            product_id => ProductId::Unknown(product_id),
+        }
+    }
+}
+
+impl From<ProductId> for u16 {
+    fn from(product_id: ProductId) -> u16 {
+        match product_id {
+            ProductId::M3S256 => 0x00CC,
+            ProductId::M3S550 => 0x00CD,
+            ProductId::M2T256 => 0x00CE,
+            ProductId::M2T550 => 0x00CF,
+            ProductId::M1T256 => 0x00D0,
+            ProductId::M1T550 => 0x00D1,
+            ProductId::M2S24v14 => 0x00D2,
+            ProductId::M2S18v18 => 0x00D3,
+            ProductId::M2S24v16 => 0x00D4,
+            ProductId::M2S18v20 => 0x00D5,
+            ProductId::Unknown(product_id) => product_id,
+        }
+    }
+}
+
+/// A feature that only some controllers (or firmware versions) support. You can check whether a
+/// specific device supports one with [`Device::firmware_supports`](crate::Device::firmware_supports).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Feature {
+    /// Measuring the current going through each motor.
+    CurrentSensing,
+    /// Limiting the current going through each motor.
+    CurrentLimiting,
+    /// Limiting how quickly motors accelerate and decelerate.
+    AccelerationLimiting,
+    /// Reporting the controller's temperature.
+    TemperatureSensing,
+}
+
+impl Feature {
+    /// Returns whether the hardware of the given controller supports this feature, regardless of
+    /// the firmware it's running.
+    pub fn supported_by(&self, controller_type: ControllerType) -> bool {
+        match self {
+            Feature::CurrentSensing => controller_type.is_current_sensing(),
+            Feature::CurrentLimiting => controller_type.is_current_limiting(),
+            Feature::AccelerationLimiting => true,
+            Feature::TemperatureSensing => controller_type.has_temperature_sensor(),
+        }
+    }
+
+    /// Returns the oldest firmware version that supports this feature, as a `(major, minor)` pair.
+    /// All of the features listed here have been available since the first firmware release
+    /// (1.00) on the controllers whose hardware supports them.
+    pub fn min_firmware_version(&self) -> (u8, u8) {
+        match self {
+            Feature::CurrentSensing
+            | Feature::CurrentLimiting
+            | Feature::AccelerationLimiting
+            | Feature::TemperatureSensing => (1, 0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every controller type, to make sure each one is covered.
+    const ALL: [ControllerType; 20] = [
+        ControllerType::M1T550,
+        ControllerType::M1U550,
+        ControllerType::M2T550,
+        ControllerType::M2U550,
+        ControllerType::M1T256,
+        ControllerType::M1U256,
+        ControllerType::M2T256,
+        ControllerType::M2U256,
+        ControllerType::M3S550,
+        ControllerType::M3H550,
+        ControllerType::M3S256,
+        ControllerType::M3H256,
+        ControllerType::M2S24v14,
+        ControllerType::M2H24v14,
+        ControllerType::M2S24v16,
+        ControllerType::M2H24v16,
+        ControllerType::M2S18v18,
+        ControllerType::M2H18v18,
+        ControllerType::M2S18v20,
+        ControllerType::M2H18v20,
+    ];
+
+    #[test]
+    fn capabilities() {
+        // (controller, current sensing, current limiting)
+        let expected = [
+            (ControllerType::M1T550, true, true),
+            (ControllerType::M1U550, true, true),
+            (ControllerType::M2T550, true, true),
+            (ControllerType::M2U550, true, true),
+            (ControllerType::M1T256, false, false),
+            (ControllerType::M1U256, false, false),
+            (ControllerType::M2T256, false, false),
+            (ControllerType::M2U256, false, false),
+            (ControllerType::M3S550, true, true),
+            (ControllerType::M3H550, true, true),
+            (ControllerType::M3S256, false, false),
+            (ControllerType::M3H256, false, false),
+            (ControllerType::M2S24v14, true, true),
+            (ControllerType::M2H24v14, true, true),
+            (ControllerType::M2S24v16, true, true),
+            (ControllerType::M2H24v16, true, true),
+            (ControllerType::M2S18v18, true, true),
+            (ControllerType::M2H18v18, true, true),
+            (ControllerType::M2S18v20, true, true),
+            (ControllerType::M2H18v20, true, true),
+        ];
+        assert_eq!(expected.map(|(controller_type, ..)| controller_type), ALL);
+        for (controller_type, sensing, limiting) in expected {
+            assert_eq!(
+                controller_type.is_current_sensing(),
+                sensing,
+                "{controller_type:?}"
+            );
+            assert_eq!(controller_type.has_current_sensing(), sensing);
+            assert_eq!(
+                controller_type.is_current_limiting(),
+                limiting,
+                "{controller_type:?}"
+            );
+            assert!(!controller_type.has_temperature_sensor());
+            assert_eq!(
+                Feature::CurrentSensing.supported_by(controller_type),
+                sensing
+            );
+            assert_eq!(
+                Feature::CurrentLimiting.supported_by(controller_type),
+                limiting
+            );
+            assert_eq!(
+                controller_type.current_sense_scale().is_some(),
+                sensing,
+                "{controller_type:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn ratings() {
+        assert_eq!(ControllerType::M2T256.max_voltage(), 48.);
+        assert_eq!(
+            ControllerType::M2T256.continuous_current_per_motor(),
+            Some(2.)
+        );
+        assert_eq!(ControllerType::M2T550.max_voltage(), 22.);
+        assert_eq!(
+            ControllerType::M2T550.continuous_current_per_motor(),
+            Some(1.5)
+        );
+        assert_eq!(ControllerType::M2S18v20.max_voltage(), 30.);
+        assert_eq!(
+            ControllerType::M2S18v20.continuous_current_per_motor(),
+            Some(20.)
+        );
+        for controller_type in ALL {
+            assert!(controller_type.continuous_current_per_motor().is_some());
+        }
+    }
+
+    #[test]
+    fn known_product_ids() {
+        assert_eq!(
+            ControllerType::from_product_id(0x00CE),
+            Some(ControllerType::M2T256)
+        );
+        assert_eq!(
+            ControllerType::from_product_id(0x00CD),
+            Some(ControllerType::M3S550)
+        );
+        assert_eq!(ControllerType::from_product_id(0x1234), None);
+    }
+
+    #[test]
+    fn product_ids_round_trip() {
+        for controller_type in ALL {
+            let product_id = controller_type.product_id();
+            let detected = ControllerType::from_product_id(product_id).unwrap();
+            assert_eq!(detected.product_id(), product_id);
+            assert_eq!(detected.motor_channels(), controller_type.motor_channels());
+        }
+    }
+
+    #[test]
+    fn product_ids() {
+        assert_eq!(ProductId::from(0x00CE), ProductId::M2T256);
+        assert_eq!(u16::from(ProductId::M2T256), 0x00CE);
+        assert_eq!(ControllerType::M2U256.product(), ProductId::M2T256);
+        assert_eq!(ProductId::from(0x1234), ProductId::Unknown(0x1234));
+        assert_eq!(u16::from(ProductId::Unknown(0x1234)), 0x1234);
+        assert_eq!(ProductId::Unknown(0x1234).controller_type(), None);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn json_round_trip() {
+        let json = serde_json::to_string(&ControllerType::M2T256).unwrap();
+        assert_eq!(
+            serde_json::from_str::<ControllerType>(&json).unwrap(),
+            ControllerType::M2T256
+        );
+        let json = serde_json::to_string(&ProductId::Unknown(0x1234)).unwrap();
+        assert_eq!(
+            serde_json::from_str::<ProductId>(&json).unwrap(),
+            ProductId::Unknown(0x1234)
+        );
+    }
 }
@@ -0,0 +1,120 @@
+/// Offset of the first byte of the settings stored in EEPROM. Offset 0 holds the factory reset
+/// code, which isn't a setting, so we leave it alone.
+#[cfg(feature = "std")]
+pub(crate) const EEPROM_CONFIG_OFFSET: u8 = 1;
+/// Number of bytes of settings stored in EEPROM, offsets 1 through 8. The rest of the EEPROM is
+/// reserved.
+#[cfg(feature = "std")]
+pub(crate) const EEPROM_CONFIG_LEN: usize = 8;
+/// Offset of the device number, which takes up two bytes.
+#[cfg(feature = "std")]
+pub(crate) const EEPROM_DEVICE_NUMBER_OFFSET: u8 = 1;
+
+/// The settings stored in the controller's EEPROM, as described in the
+/// [settings reference](https://www.pololu.com/docs/0J84). The controller only loads these when
+/// it starts up, so after writing them you need to reset it for them to take effect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EepromConfig {
+    /// The device number, which is also the 7-bit I2C address (offsets 1 and 2).
+    pub device_number: u16,
+    /// An additional device number the controller responds to, if enabled (offsets 3 and 4).
+    pub alternative_device_number: Option<u16>,
+    /// The communication options bit field, only relevant for UART (offset 5).
+    pub communication_options: u8,
+    /// The UART baud rate divider (offsets 6 and 7).
+    pub baud_divider: u16,
+    /// The UART response delay, in microseconds (offset 8).
+    pub response_delay: u8,
+}
+
+#[cfg(feature = "std")]
+impl EepromConfig {
+    /// Parses the settings from the raw EEPROM bytes starting at offset 1.
+    pub(crate) fn from_bytes(data: &[u8]) -> EepromConfig {
+        let alternative_enabled = data[2] & 0x80 != 0;
+        EepromConfig {
+            device_number: from_7bit_pair(data[0], data[1]),
+            alternative_device_number: alternative_enabled
+                .then(|| from_7bit_pair(data[2], data[3])),
+            communication_options: data[4],
+            baud_divider: u16::from_le_bytes([data[5], data[6]]),
+            response_delay: data[7],
+        }
+    }
+
+    /// Serialises the settings into the raw EEPROM bytes starting at offset 1.
+    pub(crate) fn to_bytes(self) -> [u8; EEPROM_CONFIG_LEN] {
+        let [device_low, device_high] = to_7bit_pair(self.device_number);
+        let [alternative_low, alternative_high] = match self.alternative_device_number {
+            Some(number) => {
+                let [low, high] = to_7bit_pair(number);
+                [low | 0x80, high]
+            }
+            None => [0, 0],
+        };
+        let [baud_low, baud_high] = self.baud_divider.to_le_bytes();
+        [
+            device_low,
+            device_high,
+            alternative_low,
+            alternative_high,
+            self.communication_options,
+            baud_low,
+            baud_high,
+            self.response_delay,
+        ]
+    }
+}
+
+#[cfg(feature = "std")]
+pub(crate) fn from_7bit_pair(low: u8, high: u8) -> u16 {
+    u16::from(low & 0x7F) | (u16::from(high & 0x7F) << 7)
+}
+
+#[cfg(feature = "std")]
+fn to_7bit_pair(value: u16) -> [u8; 2] {
+    [(value & 0x7F) as u8, ((value >> 7) & 0x7F) as u8]
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_round_trip() {
+        let config = EepromConfig {
+            device_number: 0x1234,
+            alternative_device_number: Some(0x21),
+            communication_options: 0x03,
+            baud_divider: 0x0271,
+            response_delay: 5,
+        };
+        let bytes = config.to_bytes();
+        // The alternative device number is enabled by bit 7 of its low byte
+        assert_eq!(bytes, [0x34, 0x24, 0xA1, 0x00, 0x03, 0x71, 0x02, 0x05]);
+        assert_eq!(EepromConfig::from_bytes(&bytes), config);
+    }
+
+    #[test]
+    fn config_round_trip_without_alternative() {
+        let config = EepromConfig {
+            device_number: 0x10,
+            alternative_device_number: None,
+            communication_options: 0,
+            baud_divider: 0,
+            response_delay: 0,
+        };
+        let bytes = config.to_bytes();
+        assert_eq!(bytes, [0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+        assert_eq!(EepromConfig::from_bytes(&bytes), config);
+    }
+
+    #[test]
+    fn alternative_disabled_ignores_number() {
+        let bytes = [0x10, 0x00, 0x21, 0x00, 0x00, 0x00, 0x00, 0x00];
+        assert_eq!(
+            EepromConfig::from_bytes(&bytes).alternative_device_number,
+            None
+        );
+    }
+}
@@ -9,7 +9,8 @@
 //! use std::time::Duration;
 //!
 //! # fn main() -> anyhow::Result<()> {
-//! let mut device = pololu_motoron::Device::new(ControllerType::M2T256, "/dev/i2c-0", 0x10)?;
+//! let mut device =
+//!     pololu_motoron::Device::new_linux(ControllerType::M2T256, "/dev/i2c-0", 0x10)?;
 //!
 //! // Get version information
 //! let version = device.firmware_version();
@@ -24,33 +25,196 @@
 //! # }
 //! ```
 //!
+//! While the above uses the Linux I2C bus, [`Device`] works with any bus implementing the
+//! [`embedded_hal`] I2C trait, so the same code can run on microcontrollers or against a fake bus
+//! in tests. The Linux support is behind the `linux` feature, which is enabled by default, and
+//! the `mock` feature adds a `MockI2c` fake bus for testing. For async buses, the `async`
+//! feature adds an `AsyncDevice`.
+//!
+//! Without the default `std` feature, the crate is `no_std` (it still needs `alloc`). [`Device`]
+//! isn't available then, but `AsyncDevice` is, as well as the command encoding itself (see
+//! [`encode_command`] and [`decode_response`]) if you want to drive the bus yourself.
+//!
 //! We recommend starting with the [`Device`] documentation.
 
-use crate::commands::{
-    decode_response, encode_command, Command, GetFirmwareVersion, SetProtocolOptions,
-};
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
+use crate::commands::{GetFirmwareVersion, SetProtocolOptions};
+#[cfg(any(feature = "std", feature = "async"))]
+use alloc::format;
+use alloc::string::String;
+#[cfg(any(feature = "std", feature = "async"))]
+use alloc::vec::Vec;
+#[cfg(any(feature = "std", feature = "async"))]
+use commands::{BrakingMode, SetBraking, SetSpeed, SpeedMode};
+#[cfg(feature = "std")]
 use commands::{
-    Reinitialise, SetAllSpeeds, SetAllSpeedsUsingBuffers, SetSpeed, SpeedMode, SpeedModeNoBuffer,
+    ClearMotorFault, CoastNow, GetVariables, MultiDeviceErrorCheck, MultiDeviceWrite, ReadEeprom,
+    Reinitialise, Reset, ResetCommandTimeout, SetAllSpeeds, SetAllSpeedsUsingBuffers, SetVariable,
+    SpeedModeNoBuffer, WriteEeprom,
+};
+#[cfg(feature = "std")]
+use eeprom::{
+    from_7bit_pair, EEPROM_CONFIG_LEN, EEPROM_CONFIG_OFFSET, EEPROM_DEVICE_NUMBER_OFFSET,
 };
-use i2cdev::core::I2CDevice;
-use i2cdev::linux::{LinuxI2CDevice, LinuxI2CError};
+use embedded_hal::i2c::ErrorKind;
+#[cfg(feature = "std")]
+use embedded_hal::i2c::{I2c, SevenBitAddress};
+#[cfg(feature = "linux")]
 use std::path::Path;
-use std::time::Duration;
+#[cfg(feature = "std")]
+use std::time::{Duration, Instant};
 
+#[cfg(feature = "async")]
+mod async_device;
+#[cfg(feature = "std")]
+mod builder;
 mod commands;
 mod controllers;
+mod eeprom;
+#[cfg(feature = "linux")]
+mod linux;
+#[cfg(any(feature = "mock", all(test, feature = "std")))]
+mod mock;
+mod scan;
+mod status;
+#[cfg(feature = "std")]
+mod supervisor;
+mod variables;
+
+#[cfg(feature = "async")]
+pub use crate::async_device::AsyncDevice;
+#[cfg(feature = "std")]
+pub use crate::builder::DeviceBuilder;
+pub use crate::commands::{
+    decode_response, encode_command, ClearLatchedStatusFlags, Command, Error as CommandsError,
+    FirmwareVersion, MultiDeviceErrorCheckResponse, Response, SetLatchedStatusFlags,
+};
+pub use crate::controllers::{ControllerType, Feature, ProductId};
+pub use crate::eeprom::EepromConfig;
+#[cfg(feature = "linux")]
+pub use crate::linux::{LinuxI2c, LinuxI2cError};
+#[cfg(feature = "mock")]
+pub use crate::mock::MockI2c;
+pub use crate::scan::scan_bus;
+#[cfg(feature = "linux")]
+pub use crate::scan::scan_linux_bus;
+pub use crate::status::{MotorStatus, MotorTelemetry, StatusFlag, Telemetry};
+#[cfg(feature = "std")]
+pub use crate::supervisor::SupervisedDevice;
+pub use crate::variables::Variable;
 
-pub use crate::commands::{ClearLatchedStatusFlags, Error as CommandsError, FirmwareVersion};
-pub use crate::controllers::ControllerType;
+/// The most bytes a single `GetVariables` command can return.
+#[cfg(feature = "std")]
+const MAX_VARIABLES_READ: usize = 32;
+/// Length of the general (device-wide) variable space, offsets 0 through 11.
+#[cfg(feature = "std")]
+const GENERAL_VARIABLES_LEN: usize = 12;
+/// Length of the per-motor variable space, offsets 0 through 34.
+#[cfg(feature = "std")]
+const MOTOR_VARIABLES_LEN: usize = 35;
+/// Offset of the (general) protocol options variable.
+#[cfg(feature = "std")]
+const PROTOCOL_OPTIONS_OFFSET: u8 = 0;
+/// Offset of the (general) status flags variable.
+#[cfg(feature = "std")]
+const STATUS_FLAGS_OFFSET: u8 = 1;
+/// The bits of the status flags variable holding the latched flags.
+#[cfg(feature = "std")]
+const LATCHED_STATUS_FLAGS_MASK: u16 = 0x3FF;
+/// Offset of the (general) input voltage variable.
+#[cfg(feature = "std")]
+const VIN_VOLTAGE_OFFSET: u8 = 3;
+/// Offset of the (per-motor) target speed variable.
+#[cfg(feature = "std")]
+const TARGET_SPEED_OFFSET: u8 = 2;
+/// Offset of the (per-motor) current speed variable.
+#[cfg(feature = "std")]
+const CURRENT_SPEED_OFFSET: u8 = 6;
+/// Offset of the (per-motor) processed current sense variable.
+#[cfg(feature = "std")]
+const CURRENT_SENSE_PROCESSED_OFFSET: u8 = 30;
+/// How often [`Device::reset_and_wait`] tries to talk to the device while it's rebooting.
+#[cfg(feature = "std")]
+const RESET_POLL_INTERVAL: Duration = Duration::from_millis(1);
+/// How long to wait before the first retry of a failed I2C transfer. Each further retry waits
+/// twice as long as the previous one.
+#[cfg(feature = "std")]
+const RETRY_BACKOFF: Duration = Duration::from_millis(1);
+
+/// What response buffers are filled with before reading into them. A read that gets no data from
+/// the device either leaves the buffer untouched or, with nothing driving the bus, reads the
+/// pull-ups as 0xFF, so either way it comes back as all 0xFF. Responses with a CRC can't look like
+/// that, since the CRC byte is only 7 bits long.
+#[cfg(feature = "std")]
+const EMPTY_READ_FILL: u8 = 0xFF;
 
 /// Represents a Pololu Motoron motor controller. Use this to control a single motor controller on
-/// a given bus.
-pub struct Device {
-    device: LinuxI2CDevice,
+/// a given bus. The bus can be anything implementing the [`embedded_hal`] I2C trait, such as
+/// [`LinuxI2c`] on Linux.
+///
+/// On power-up, the controller always starts with its motors coasting and with the reset flag
+/// latched, which makes it ignore speed commands until the flag is cleared (see
+/// [`Device::clear_latched_status_flags`]). This startup behaviour is fixed in the firmware and
+/// there is no EEPROM setting to change it.
+#[cfg(feature = "std")]
+pub struct Device<I: I2c> {
+    device: I,
+    address: SevenBitAddress,
     controller_type: ControllerType,
     cmd_crc: bool,
     res_crc: bool,
     i2c_general_call: bool,
+    response_delay: Duration,
+    reset_delay: Duration,
+    crc_mismatch_retries: u8,
+    retry_empty_reads: bool,
+    retries: u8,
+    error_checking: bool,
+    reject_duplicate_motors: bool,
+    min_command_interval: Duration,
+    max_write_len: Option<usize>,
+    guard_reset_latch: bool,
+    coast_on_drop: bool,
+    reference_mv: u16,
+    reset_latch_checked: bool,
+    last_speed_command: Option<Instant>,
+    known_speeds: Option<Vec<i16>>,
+    max_speed: i16,
+    max_rpm: Vec<Option<f32>>,
+    inverted: Vec<bool>,
+    firmware_version: Option<FirmwareVersion>,
+    last_response: Option<Vec<u8>>,
+    recording: Option<(Instant, Box<dyn std::io::Write + Send>)>,
+}
+
+/// Coasts the motors if the device was built with [`DeviceBuilder::coast_on_drop`]. This is only
+/// best effort, so any error is ignored.
+#[cfg(feature = "std")]
+impl<I: I2c> Drop for Device<I> {
+    fn drop(&mut self) {
+        if self.coast_on_drop {
+            let _ = self.coast_now();
+        }
+    }
+}
+
+/// Only prints the device's configuration, leaving out the bus itself (which, on Linux, is just an
+/// open file). This never talks to the device.
+#[cfg(feature = "std")]
+impl<I: I2c> std::fmt::Debug for Device<I> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Device")
+            .field("address", &self.address)
+            .field("controller_type", &self.controller_type)
+            .field("cmd_crc", &self.cmd_crc)
+            .field("res_crc", &self.res_crc)
+            .field("i2c_general_call", &self.i2c_general_call)
+            .finish_non_exhaustive()
+    }
 }
 
 /// The generic error returned by all functions in this module.
@@ -59,7 +223,7 @@ pub enum Error {
     /// Any errors returned by the I2C bus/device itself. Includes permission errors, resource busy
     /// errors, among others
     #[error("I2C error: {0}")]
-    I2c(#[from] LinuxI2CError),
+    I2c(#[from] I2cError),
 
     /// Any errors related to the command itself. Please refer to [`CommandsError`] for more
     /// details.
@@ -68,7 +232,8 @@ pub enum Error {
 
     /// Returned when the speed provided to one of the motor speed functions is out of range. We
     /// expect speed to be in the range `[-1.0, 1.0]`, so if it's not this error is returned with
-    /// the incorrect speed included.
+    /// the incorrect speed included. This includes NaN, which usually points to a bug in the
+    /// control code, so it's reported rather than silently treated as a speed of 0.
     #[error("speed provided outside of [-1.0, 1.0] range, value: {0}")]
     InvalidSpeed(f32),
 
@@ -86,42 +251,177 @@ pub enum Error {
         "in setting all speeds, you provided {provided} speeds, but this controller has {actual} motors"
     )]
     IncorrectNumberSpeeds { provided: u8, actual: u8 },
+
+    /// Returned when a braking amount is outside of the `[0.0, 1.0]` range.
+    #[error("braking amount provided outside of [0.0, 1.0] range, value: {0}")]
+    InvalidBrakingAmount(f32),
+
+    /// Returned when the deadband provided to [`Device::set_speed_with_deadband`] is outside of the
+    /// `[0.0, 1.0)` range.
+    #[error("deadband provided outside of [0.0, 1.0) range, value: {0}")]
+    InvalidDeadband(f32),
+
+    /// Returned by [`Device::set_max_rpm`] when the maximum RPM isn't a positive, finite number.
+    #[error("maximum RPM must be positive and finite, value: {0}")]
+    InvalidMaxRpm(f32),
+
+    /// Returned by [`Device::set_speed_rpm`] if the maximum RPM of the motor hasn't been set with
+    /// [`Device::set_max_rpm`].
+    #[error("the maximum RPM of motor {0} hasn't been set")]
+    MaxRpmNotSet(u8),
+
+    /// Returned by [`Device::set_multi_speed`] when the same motor index is provided more than
+    /// once, if the device was built with [`DeviceBuilder::reject_duplicate_motors`].
+    #[error("motor {0} was provided more than once")]
+    DuplicateMotorIndex(u8),
+
+    /// Returned by speed commands when the reset flag is latched, if the device was built with
+    /// [`DeviceBuilder::guard_reset_latch`]. While the flag is latched, the controller silently
+    /// ignores speed commands, so clear it with [`Device::clear_reset_flag`] first.
+    #[error("the reset flag is latched, so speed commands would be ignored until it's cleared")]
+    ResetLatchActive,
+
+    /// Returned when the controller reported a protocol or CRC error for a command, if error
+    /// checking is enabled with [`Device::set_error_checking`]. Includes the raw status flags the
+    /// controller reported.
+    #[error("controller reported an error for the last command, status flags: {0:#06x}")]
+    ProtocolError(u16),
+
+    /// Returned by [`Device::reset_and_wait`] when the device didn't answer in time after being
+    /// reset.
+    #[error("device didn't come back after being reset")]
+    ResetTimeout,
+
+    /// Returned when an I2C address doesn't fit in 7 bits (i.e. it's above 0x7F). Motoron
+    /// controllers only support 7-bit addressing.
+    #[error("invalid I2C address {0:#04x}, must be between 0 and 0x7F")]
+    InvalidAddress(u8),
+
+    /// Returned when a function needs a feature that the hardware of this controller type doesn't
+    /// have, such as setting the current limit on a controller that can't limit current.
+    #[error("this controller doesn't support {0:?}")]
+    UnsupportedFeature(Feature),
+
+    /// Returned when a command is longer than the bus can write in a single transaction, as
+    /// configured with [`DeviceBuilder::max_write_len`]. Includes the length of the command,
+    /// including its CRC byte, and the maximum.
+    #[error("command is {len} bytes long, but the bus can only write {max} bytes at a time")]
+    CommandTooLong { len: usize, max: usize },
+
+    /// Returned when a response kept failing its CRC check after retrying it as many times as was
+    /// configured with [`DeviceBuilder::crc_mismatch_retry`]. Includes the total number of reads
+    /// attempted and the error from the last one.
+    #[error("response crc check failed after {attempts} attempts: {source}")]
+    CrcRetriesExhausted {
+        attempts: u16,
+        source: CommandsError,
+    },
+
+    /// Returned by [`Device::verify_controller_type`] when the connected controller isn't the
+    /// [`ControllerType`] this device was opened with. Includes the controller type this device
+    /// was opened with and the one detected from the firmware.
+    #[error("expected a {expected:?} controller, but the firmware reports a {actual:?}")]
+    ControllerMismatch {
+        expected: ControllerType,
+        actual: ControllerType,
+    },
+
+    /// Returned by [`Device::verify_channel_count`] when the connected controller doesn't have as
+    /// many motor channels as the [`ControllerType`] this device was opened with. Includes the
+    /// channel count of the controller type this device was opened with and the detected one.
+    #[error("expected {expected} motor channels, but the controller has {actual}")]
+    ChannelCountMismatch { expected: u8, actual: u8 },
+}
+
+pub type Result<T = (), E = Error> = core::result::Result<T, E>;
+
+/// An error returned by the I2C bus. Each bus implementation has its own error type, so to keep
+/// [`Error`] the same no matter the bus, we only keep the kind of error and a description of the
+/// original one.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{message} ({kind})")]
+pub struct I2cError {
+    /// The kind of error, as reported by the bus.
+    pub kind: ErrorKind,
+    /// The original error, formatted with its `Debug` implementation.
+    pub message: String,
+}
+
+#[cfg(any(feature = "std", feature = "async"))]
+impl I2cError {
+    fn new<E: embedded_hal::i2c::Error>(error: E) -> I2cError {
+        I2cError {
+            kind: error.kind(),
+            message: format!("{error:?}"),
+        }
+    }
 }
 
-pub type Result<T = (), E = Error> = std::result::Result<T, E>;
+/// What to do with a single motor, as used by [`Device::apply_actions`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MotorAction {
+    /// Drive the motor at the given speed, between -1.0 and 1.0.
+    Speed(f32),
+    /// Stop the motor, braking by the given amount, between 0.0 (coasting) and 1.0 (full braking).
+    Brake(f32),
+    /// Stop the motor and let it spin down freely.
+    Coast,
+}
 
-impl Device {
-    /// Create a new device object.
+#[cfg(feature = "linux")]
+impl Device<LinuxI2c> {
+    /// Create a new device object on a Linux I2C bus.
     ///
     /// # Arguments
     /// * `controller_type` - The type of motor controller being commanded. While the protocol
-    ///                       between different Pololu Motoron controllers is the same, this
-    ///                       provides us with limits and features of yous specific controller,
-    ///                       such as the number of motors available.
+    ///   between different Pololu Motoron controllers is the same, this
+    ///   provides us with limits and features of yous specific controller,
+    ///   such as the number of motors available.
     /// * `device`          - Represents the device file of the I2C bus. Usually something like
-    ///                       `/dev/i2c-0`.
-    /// * `address`         - The I2C address of the device we're talking to. If unconfigured, it
-    ///                       will be 0x10 (aka 16).
-    pub fn new<P: AsRef<Path>>(
+    ///   `/dev/i2c-0`.
+    /// * `address`         - The 7-bit I2C address of the device we're talking to. If
+    ///   unconfigured, it will be 0x10 (aka 16). Addresses above 0x7F return
+    ///   [`Error::InvalidAddress`].
+    ///
+    /// If you need to tweak any of the defaults, use a [`DeviceBuilder`] instead.
+    pub fn new_linux<P: AsRef<Path>>(
         controller_type: ControllerType,
         device: P,
-        address: u16,
-    ) -> Result<Device> {
-        let mut device = Device {
-            device: LinuxI2CDevice::new(device, address)?,
-            controller_type,
-            cmd_crc: true,
-            res_crc: true,
-            i2c_general_call: true,
-        };
-        device.write_protocol_options()?;
-        Ok(device)
+        address: SevenBitAddress,
+    ) -> Result<Device<LinuxI2c>> {
+        DeviceBuilder::new().build_linux(controller_type, device, address)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<I: I2c> Device<I> {
+    /// Create a new device object on the given I2C bus.
+    ///
+    /// # Arguments
+    /// * `controller_type` - The type of motor controller being commanded. While the protocol
+    ///   between different Pololu Motoron controllers is the same, this
+    ///   provides us with limits and features of yous specific controller,
+    ///   such as the number of motors available.
+    /// * `i2c`             - The I2C bus the controller is on.
+    /// * `address`         - The 7-bit I2C address of the device we're talking to. If
+    ///   unconfigured, it will be 0x10 (aka 16). Addresses above 0x7F return
+    ///   [`Error::InvalidAddress`].
+    ///
+    /// If you need to tweak any of the defaults, use a [`DeviceBuilder`] instead.
+    pub fn new(
+        controller_type: ControllerType,
+        i2c: I,
+        address: SevenBitAddress,
+    ) -> Result<Device<I>> {
+        DeviceBuilder::new().build(controller_type, i2c, address)
     }
 
     /// Reinitialises the device and returns all variables back to default values. We do re-write
     /// the protocol options, as well as clearing the reset status flag before returning
     pub fn reinitialise(&mut self) -> Result {
         self.write_command(&Reinitialise)?;
+        self.known_speeds = None;
+        self.firmware_version = None;
         self.write_protocol_options()?;
         self.clear_latched_status_flags(ClearLatchedStatusFlags {
             reset: true,
@@ -129,6 +429,19 @@ impl Device {
         })
     }
 
+    /// Same as [`Device::reinitialise`], but instead of restoring the protocol options (CRC and
+    /// general call settings) cached in this object, it restores the ones the device is currently
+    /// using. This takes an extra read of the protocol options variable before reinitialising, and
+    /// updates the cached options to match. It is useful when something else (say, another
+    /// process) configured the device, so the cached options might be stale.
+    pub fn reinitialise_preserving_device_options(&mut self) -> Result {
+        let options = self.read_variables(0, PROTOCOL_OPTIONS_OFFSET, 1)?[0];
+        self.cmd_crc = options & 0b001 != 0;
+        self.res_crc = options & 0b010 != 0;
+        self.i2c_general_call = options & 0b100 != 0;
+        self.reinitialise()
+    }
+
     /// This disables all CRC checks on the device, both command and resposnse checks
     pub fn disable_crc(&mut self) -> Result {
         self.cmd_crc = false;
@@ -143,11 +456,35 @@ impl Device {
         self.write_protocol_options()
     }
 
-    /// Resets the device fully, similar to a power reboot. We also re-write the protocol options
-    /// and clear the reset status flag before returning.
+    /// Enables or disables CRC checks on commands sent to the device, leaving the rest of the
+    /// protocol options untouched.
+    pub fn set_command_crc(&mut self, enabled: bool) -> Result {
+        self.cmd_crc = enabled;
+        self.write_protocol_options()
+    }
+
+    /// Enables or disables CRC bytes on responses sent by the device, leaving the rest of the
+    /// protocol options untouched.
+    pub fn set_response_crc(&mut self, enabled: bool) -> Result {
+        self.res_crc = enabled;
+        self.write_protocol_options()
+    }
+
+    /// Enables or disables the device's response to the I2C general call address (0), leaving the
+    /// rest of the protocol options untouched. Disable this if you don't want broadcast commands
+    /// on the bus to reach this controller.
+    pub fn set_general_call(&mut self, enabled: bool) -> Result {
+        self.i2c_general_call = enabled;
+        self.write_protocol_options()
+    }
+
+    /// Resets the device fully, similar to a power reboot, unlike [`Device::reinitialise`], which
+    /// only resets its variables. We wait for the device to boot back up (see
+    /// [`DeviceBuilder::reset_delay`]), then re-write the protocol options and clear the reset
+    /// status flag before returning.
     pub fn reset(&mut self) -> Result {
-        self.write_command(&Reinitialise)?;
-        std::thread::sleep(Duration::from_millis(10));
+        self.write_reset()?;
+        std::thread::sleep(self.reset_delay);
         self.write_protocol_options()?;
         self.clear_latched_status_flags(ClearLatchedStatusFlags {
             reset: true,
@@ -155,51 +492,308 @@ impl Device {
         })
     }
 
+    /// Same as [`Device::reset`], but instead of waiting a fixed delay for the device to boot back
+    /// up, it keeps trying to talk to it (re-writing the protocol options and reading the firmware
+    /// version) until it answers, which is both faster and more reliable. If the device still
+    /// hasn't answered after `timeout`, [`Error::ResetTimeout`] is returned.
+    pub fn reset_and_wait(&mut self, timeout: Duration) -> Result {
+        self.write_reset()?;
+        let start = Instant::now();
+        while let Err(e) = self
+            .write_protocol_options()
+            .and_then(|()| self.refresh_firmware_version())
+        {
+            if start.elapsed() >= timeout {
+                log::debug!("Device didn't come back after reset, last error: {e}");
+                return Err(Error::ResetTimeout);
+            }
+            std::thread::sleep(RESET_POLL_INTERVAL);
+        }
+        self.clear_latched_status_flags(ClearLatchedStatusFlags {
+            reset: true,
+            ..Default::default()
+        })
+    }
+
     /// Call this function to set the speed of a specific motor. Note that speeds reset back to 0
-    /// if new commands are not sent in a long time, so expect to send this on a loop if you want
-    /// to keep movement.
+    /// if new commands are not sent in a long time (the command timeout), so expect to send this
+    /// on a loop if you want to keep movement, or see [`Device::reset_command_timeout`].
     ///
     /// # Arguments
     /// * `motor_idx` - The index of the motor, zero-indexed. The most motors supported by one of
-    ///                 these devices is 3, so it should be no higher than 2.
+    ///   these devices is 3, so it should be no higher than 2.
     /// * `speed`     - The speed to set the motor to, as a floating point between -1.0 and 1.0.
     pub fn set_speed(&mut self, motor_idx: u8, speed: f32) -> Result {
         let cmd = self.get_speed_cmd(motor_idx, speed, SpeedMode::Normal)?;
+        self.before_speed_command()?;
+        self.write_command(&cmd)
+    }
+
+    /// Records the RPM a motor spins at when driven at full speed, so it can be driven with
+    /// [`Device::set_speed_rpm`]. This is only used to convert RPMs to speeds, and isn't sent to
+    /// the controller.
+    ///
+    /// # Arguments
+    /// * `motor_idx` - The index of the motor, zero-indexed.
+    /// * `max_rpm`   - The RPM of the motor at full speed, which must be positive.
+    pub fn set_max_rpm(&mut self, motor_idx: u8, max_rpm: f32) -> Result {
+        self.check_motor_idx(motor_idx)?;
+        if !max_rpm.is_finite() || max_rpm <= 0. {
+            return Err(Error::InvalidMaxRpm(max_rpm));
+        }
+        self.max_rpm[usize::from(motor_idx)] = Some(max_rpm);
+        Ok(())
+    }
+
+    /// Same as [`Device::set_speed`], but takes a speed in RPM, which gets converted to a speed
+    /// using the maximum RPM set with [`Device::set_max_rpm`], i.e. `rpm / max_rpm`. Negative RPMs
+    /// drive the motor in reverse. As with any other speed, if the RPM is higher than the maximum,
+    /// [`Error::InvalidSpeed`] is returned.
+    ///
+    /// # Arguments
+    /// * `motor_idx` - The index of the motor, zero-indexed.
+    /// * `rpm`       - The RPM to drive the motor at.
+    pub fn set_speed_rpm(&mut self, motor_idx: u8, rpm: f32) -> Result {
+        self.check_motor_idx(motor_idx)?;
+        let max_rpm = self.max_rpm[usize::from(motor_idx)].ok_or(Error::MaxRpmNotSet(motor_idx))?;
+        self.set_speed(motor_idx, rpm / max_rpm)
+    }
+
+    /// Sets whether a motor's direction is inverted. Robots often have motors mounted mirrored, so
+    /// driving one side "forward" actually drives it in reverse. Once a motor is inverted, every
+    /// speed sent to it is negated, so a positive speed always means forward for your robot.
+    /// Speeds read back from the controller, such as in [`Device::telemetry`], are negated back.
+    /// Braking is the same in both directions, so it isn't affected.
+    ///
+    /// # Arguments
+    /// * `motor_idx` - The index of the motor, zero-indexed.
+    /// * `inverted`  - Whether the motor's direction is inverted.
+    pub fn set_motor_inverted(&mut self, motor_idx: u8, inverted: bool) -> Result {
+        self.check_motor_idx(motor_idx)?;
+        self.inverted[usize::from(motor_idx)] = inverted;
+        Ok(())
+    }
+
+    /// Same as [`Device::set_speed`], but returns the speed the motor actually ends up with once
+    /// it's quantized to the controller's raw steps (see [`Device::quantize_speed`]). This lets
+    /// closed-loop controllers account for the quantization error.
+    pub fn set_speed_quantized(&mut self, motor_idx: u8, speed: f32) -> Result<f32> {
+        self.set_speed(motor_idx, speed)?;
+        Ok(self.quantize_speed(speed))
+    }
+
+    /// Same as [`Device::set_speed`], but instead of returning an error when the speed is outside
+    /// of the `[-1.0, 1.0]` range, it gets clamped to that range. This is handy for control loops
+    /// whose output can end up slightly out of range because of floating point error. NaN and
+    /// infinite speeds aren't clamped, as they usually point to a bug, so they still return
+    /// [`Error::InvalidSpeed`].
+    pub fn set_speed_clamped(&mut self, motor_idx: u8, speed: f32) -> Result {
+        self.set_speed(motor_idx, clamp_speed(speed))
+    }
+
+    /// Same as [`Device::set_speed`], but the new speed is applied right away, ignoring the
+    /// acceleration and deceleration limits configured on the controller. This is useful when
+    /// recovering from an emergency stop, where you want the motor to jump to the commanded speed
+    /// instantly.
+    pub fn set_speed_now(&mut self, motor_idx: u8, speed: f32) -> Result {
+        let cmd = self.get_speed_cmd(motor_idx, speed, SpeedMode::Now)?;
+        self.before_speed_command()?;
         self.write_command(&cmd)
     }
 
+    /// Changes the speed of a motor by `delta`, relative to its current target speed, and returns
+    /// the new speed. This is handy for things like keyboard teleoperation, e.g. "press up to go a
+    /// bit faster". The current target speed is read back from the controller, so this works
+    /// regardless of how the speed was last set. The new speed is clamped to `[-1.0, 1.0]`.
+    ///
+    /// # Arguments
+    /// * `motor_idx` - The index of the motor, zero-indexed.
+    /// * `delta`     - How much to change the speed by. Positive values move the speed towards
+    ///   full forward and negative ones towards full reverse.
+    pub fn nudge(&mut self, motor_idx: u8, delta: f32) -> Result<f32> {
+        self.check_motor_idx(motor_idx)?;
+        let data = self.read_variables(motor_idx + 1, TARGET_SPEED_OFFSET, 2)?;
+        let current_speed = self.raw_to_speed(motor_idx, i16::from_le_bytes([data[0], data[1]]));
+        let speed = (current_speed + delta).clamp(-1., 1.);
+        self.set_speed(motor_idx, speed)?;
+        Ok(speed)
+    }
+
+    /// Same as [`Device::set_speed`], but any speed whose magnitude is below `deadband` is sent as
+    /// exactly 0. Joysticks rarely rest at exactly zero, and the tiny speeds they produce can make
+    /// motors hum or creep, which a deadband gets rid of.
+    ///
+    /// Note that the controller has its own starting speed setting, which makes it bump any
+    /// non-zero speed below it up to the starting speed. Speeds that make it through the deadband
+    /// are still subject to it, so you may want the deadband to be at least as big as the starting
+    /// speed.
+    ///
+    /// # Arguments
+    /// * `motor_idx` - The index of the motor, zero-indexed.
+    /// * `speed`     - The speed to set the motor to, as a floating point between -1.0 and 1.0.
+    /// * `deadband`  - Speeds with a magnitude below this are treated as 0. Must be in the
+    ///   `[0.0, 1.0)` range, or [`Error::InvalidDeadband`] is returned.
+    pub fn set_speed_with_deadband(&mut self, motor_idx: u8, speed: f32, deadband: f32) -> Result {
+        if !(0. ..1.).contains(&deadband) {
+            return Err(Error::InvalidDeadband(deadband));
+        }
+        let speed = if speed.abs() < deadband { 0. } else { speed };
+        self.set_speed(motor_idx, speed)
+    }
+
     /// Call this function to set the speed of all motors simultaneously. Note that, much like
     /// [`Device::set_speed`], speeds reset back to 0 if new commands are not sent in a long time,
     /// so expect to send this on a loop if you want to keep movement.
     ///
     /// # Arguments
     /// * `speeds` - The speeds to set the motors to, as floating points between -1.0 and 1.0. Note
-    ///              that the length of the array MUST match the number of supported motor channels
-    ///              for your controller type. If you're not sure how many that is, you can call
-    ///              the [`ControllerType::motor_channels`] function.
+    ///   that the length of the array MUST match the number of supported motor channels
+    ///   for your controller type. If you're not sure how many that is, you can call
+    ///   the [`ControllerType::motor_channels`] function.
     pub fn set_all_speeds(&mut self, speeds: &[f32]) -> Result {
+        let cmd = SetAllSpeeds {
+            mode: SpeedMode::Normal,
+            speeds: self.get_raw_speeds(speeds)?,
+        };
+        self.before_speed_command()?;
+        self.write_command(&cmd)
+    }
+
+    /// Same as [`Device::set_all_speeds`], but the new speeds are applied right away, ignoring the
+    /// acceleration and deceleration limits configured on the controller. This is meant for
+    /// emergency maneuvers, where ramping up or down would take too long.
+    pub fn set_all_speeds_now(&mut self, speeds: &[f32]) -> Result {
+        let cmd = SetAllSpeeds {
+            mode: SpeedMode::Now,
+            speeds: self.get_raw_speeds(speeds)?,
+        };
+        self.before_speed_command()?;
+        self.write_command(&cmd)
+    }
+
+    /// Same as [`Device::set_all_speeds`], but also returns how long the I2C transaction took. This
+    /// only times the write itself, so it doesn't include validating the speeds or waiting for the
+    /// minimum command interval (see [`DeviceBuilder::min_command_interval`]).
+    pub fn set_all_speeds_timed(&mut self, speeds: &[f32]) -> Result<Duration> {
+        let cmd = SetAllSpeeds {
+            mode: SpeedMode::Normal,
+            speeds: self.get_raw_speeds(speeds)?,
+        };
+        self.before_speed_command()?;
+        let start = Instant::now();
+        self.write_command(&cmd)?;
+        Ok(start.elapsed())
+    }
+
+    /// Same as [`Device::set_all_speeds`], but drives every motor at the same speed, so you don't
+    /// need to know how many motors the controller has. This is handy for stopping, or for spinning
+    /// in place.
+    pub fn set_all_same_speed(&mut self, speed: f32) -> Result {
+        let speeds = vec![speed; usize::from(self.controller_type.motor_channels())];
+        self.set_all_speeds(&speeds)
+    }
+
+    /// Same as [`Device::set_all_speeds`], but takes a fixed-size array, which reads a bit cleaner
+    /// when you know how many motors your controller has, e.g. `device.set_speeds([0.5, 0.8])`.
+    /// The array length is still checked against the number of motor channels at runtime, and
+    /// [`Error::IncorrectNumberSpeeds`] is returned if they don't match.
+    pub fn set_speeds<const N: usize>(&mut self, speeds: [f32; N]) -> Result {
+        self.set_all_speeds(&speeds)
+    }
+
+    /// Same as [`Device::set_all_speeds`], but instead of returning an error when a speed is
+    /// outside of the `[-1.0, 1.0]` range, it gets clamped to that range. This is handy for control
+    /// loops whose output occasionally overshoots the range but that shouldn't abort because of
+    /// it.
+    ///
+    /// Returns the speeds that were actually sent, after clamping, so you can tell which (if any)
+    /// were saturated. As with [`Device::set_speed_clamped`], NaN and infinite speeds still return
+    /// [`Error::InvalidSpeed`].
+    pub fn set_all_speeds_saturating(&mut self, speeds: &[f32]) -> Result<Vec<f32>> {
+        let speeds: Vec<f32> = speeds.iter().map(|speed| clamp_speed(*speed)).collect();
+        self.set_all_speeds(&speeds)?;
+        Ok(speeds)
+    }
+
+    /// Same as [`Device::set_all_speeds_saturating`], for when you don't need the clamped speeds
+    /// back. See also [`Device::set_speed_clamped`].
+    pub fn set_all_speeds_clamped(&mut self, speeds: &[f32]) -> Result {
+        self.set_all_speeds_saturating(speeds).map(|_| ())
+    }
+
+    /// Drives a three-wheeled holonomic (omni wheel, or "kiwi drive") platform by mixing the desired
+    /// body velocity into the speeds of the three motors. This requires a controller with 3 motor
+    /// channels, and returns [`Error::IncorrectNumberSpeeds`] otherwise.
+    ///
+    /// We assume the wheels are evenly spaced around the robot, with motor 0 on the positive X
+    /// axis, and motors 1 and 2 at 120° and 240° counterclockwise from it. Each wheel is assumed to
+    /// push counterclockwise (seen from above) when driven at a positive speed. With those
+    /// assumptions, the speed of motor `i` at angle `θ` is `-sin(θ) * vx + cos(θ) * vy + omega`.
+    /// If that makes any motor exceed `±1.0`, all speeds get scaled down by the same factor, so the
+    /// direction of travel is preserved.
+    ///
+    /// # Arguments
+    /// * `vx`    - The desired velocity along the X axis, normalised to `[-1.0, 1.0]`.
+    /// * `vy`    - The desired velocity along the Y axis, normalised to `[-1.0, 1.0]`.
+    /// * `omega` - The desired rotation speed, counterclockwise, normalised to `[-1.0, 1.0]`.
+    pub fn drive_omni(&mut self, vx: f32, vy: f32, omega: f32) -> Result {
         let num_motors = self.controller_type.motor_channels();
-        if usize::from(num_motors) != speeds.len() {
+        if num_motors != 3 {
             return Err(Error::IncorrectNumberSpeeds {
-                provided: speeds.len().try_into().unwrap(),
+                provided: 3,
                 actual: num_motors,
             });
         }
-        let speeds = speeds
-            .into_iter()
-            .map(|speed| {
-                if speed.abs() > 1. {
-                    Err(Error::InvalidSpeed(*speed))
-                } else {
-                    Ok((*speed * 800.) as i16)
-                }
-            })
-            .collect::<Result<_>>()?;
-        let cmd = SetAllSpeeds {
-            mode: SpeedMode::Normal,
-            speeds,
-        };
-        self.write_command(&cmd)
+        let mut speeds = [0., 120f32, 240f32]
+            .map(|angle| -angle.to_radians().sin() * vx + angle.to_radians().cos() * vy + omega);
+        let max_speed = speeds.iter().fold(1f32, |max, speed| max.max(speed.abs()));
+        for speed in &mut speeds {
+            *speed /= max_speed;
+        }
+        self.set_all_speeds(&speeds)
+    }
+
+    /// Records the speeds you know the motors are currently set to, without sending anything to the
+    /// controller. Subsequent calls to [`Device::set_all_speeds_delta`] will skip any motor whose
+    /// speed matches this baseline. This is useful right after opening or resetting the device, if
+    /// you know the controller powered up with all its motors stopped, to avoid the first delta
+    /// call re-sending every speed.
+    ///
+    /// # Arguments
+    /// * `speeds` - The speeds the motors are known to be set to, as floating points between -1.0
+    ///   and 1.0. Much like [`Device::set_all_speeds`], the length must match the
+    ///   number of motor channels on your controller.
+    pub fn set_baseline_speeds(&mut self, speeds: &[f32]) -> Result {
+        self.known_speeds = Some(self.get_raw_speeds(speeds)?);
+        Ok(())
+    }
+
+    /// Same as [`Device::set_all_speeds`], except that only the motors whose speed differs from
+    /// the last known speed get sent a command, each with its own `SetSpeed` command. The last
+    /// known speeds are the ones from the previous call to this function, or the ones recorded by
+    /// [`Device::set_baseline_speeds`]. If nothing is known yet, all speeds are sent.
+    ///
+    /// Note that only this function and [`Device::set_baseline_speeds`] update the last known
+    /// speeds, so if you mix this with other speed commands, set the baseline again afterwards.
+    /// Also keep in mind that skipping motors means they won't reset the command timeout, so you
+    /// will want to keep the controller alive in some other way (see [`Device::supervise`]).
+    pub fn set_all_speeds_delta(&mut self, speeds: &[f32]) -> Result {
+        let speeds = self.get_raw_speeds(speeds)?;
+        self.before_speed_command()?;
+        let known_speeds = self.known_speeds.take();
+        for (motor, speed) in speeds.iter().enumerate() {
+            if known_speeds.as_ref().map(|known| known[motor]) == Some(*speed) {
+                continue;
+            }
+            let cmd = SetSpeed {
+                mode: SpeedMode::Normal,
+                motor: u8::try_from(motor).expect("at most 3 motors") + 1,
+                speed: *speed,
+            };
+            self.write_command(&cmd)?;
+        }
+        self.known_speeds = Some(speeds);
+        Ok(())
     }
 
     /// Call this function to set the speed of multiple motors simultaneously. Note that, much like
@@ -208,80 +802,2490 @@ impl Device {
     ///
     /// # Arguments
     /// * `speeds` - A list of pairs of motor indeces and speeds to set the motors to, as floating
-    ///              points between -1.0 and 1.0. Note that the indeces must be between 0 and the
-    ///              max number of motors on your specific controller. If you provide the same
-    ///              index more than once, we will simply send an additional command that will
-    ///              override the first, but we recommend against it as it wastes bandwidth and
-    ///              time on the i2c bus.
+    ///   points between -1.0 and 1.0. Note that the indeces must be between 0 and the
+    ///   max number of motors on your specific controller. If you provide the same
+    ///   index more than once, only the last speed for it is sent, unless the device
+    ///   was built with [`DeviceBuilder::reject_duplicate_motors`], in which case
+    ///   [`Error::DuplicateMotorIndex`] is returned and nothing is sent.
+    ///
+    /// Every command is validated and encoded before any of them is sent, so invalid speeds or
+    /// motor indices don't leave some motors with buffered speeds and others without. Only a bus
+    /// error halfway through can.
     pub fn set_multi_speed(&mut self, speeds: &[(u8, f32)]) -> Result {
+        self.set_multi_speed_with_mode(speeds, SpeedModeNoBuffer::Normal)
+    }
+
+    /// Same as [`Device::set_multi_speed`], but takes the speeds as a map from motor index to speed,
+    /// which is sometimes more natural for sparse updates and can't have the same index twice.
+    /// The speeds are sent in order of motor index.
+    pub fn set_speeds_map(&mut self, speeds: &std::collections::HashMap<u8, f32>) -> Result {
+        let mut speeds: Vec<(u8, f32)> = speeds.iter().map(|(idx, speed)| (*idx, *speed)).collect();
+        speeds.sort_unstable_by_key(|(idx, _)| *idx);
+        self.set_multi_speed(&speeds)
+    }
+
+    /// Same as [`Device::set_multi_speed`], but the buffered speeds are applied right away when
+    /// committed, ignoring the acceleration and deceleration limits configured on the controller.
+    /// This is useful for coordinated manoeuvres that must take effect without any ramping.
+    pub fn set_multi_speed_now(&mut self, speeds: &[(u8, f32)]) -> Result {
+        self.set_multi_speed_with_mode(speeds, SpeedModeNoBuffer::Now)
+    }
+
+    fn set_multi_speed_with_mode(
+        &mut self,
+        speeds: &[(u8, f32)],
+        mode: SpeedModeNoBuffer,
+    ) -> Result {
+        let mut deduped: Vec<(u8, f32)> = Vec::with_capacity(speeds.len());
+        for (motor_idx, speed) in speeds {
+            match deduped.iter_mut().find(|(idx, _)| idx == motor_idx) {
+                Some(_) if self.reject_duplicate_motors => {
+                    return Err(Error::DuplicateMotorIndex(*motor_idx))
+                }
+                Some(existing) => {
+                    log::trace!(
+                        "Motor {motor_idx} provided more than once, keeping the last speed {speed}"
+                    );
+                    existing.1 = *speed;
+                }
+                None => deduped.push((*motor_idx, *speed)),
+            }
+        }
+
         // First buffer all the requested speeds
-        let cmds = speeds
+        let mut frames = deduped
             .into_iter()
-            .map(|(motor_idx, speed)| self.get_speed_cmd(*motor_idx, *speed, SpeedMode::Buffered))
+            .map(|(motor_idx, speed)| {
+                self.encode(&self.get_speed_cmd(motor_idx, speed, SpeedMode::Buffered)?)
+            })
             .collect::<Result<Vec<_>>>()?;
-        for cmd in cmds {
-            self.write_command(&cmd)?;
-        }
 
         // Then commit them to the controller for simultaneous action
-        let cmd = SetAllSpeedsUsingBuffers {
-            mode: SpeedModeNoBuffer::Normal,
-        };
+        frames.push(self.encode(&SetAllSpeedsUsingBuffers { mode })?);
+
+        self.before_speed_command()?;
+        for frame in frames {
+            self.write_frame(&frame)?;
+        }
+        if self.error_checking {
+            self.check_protocol_errors()?;
+        }
+        Ok(())
+    }
+
+    /// Stops a motor, braking it by the given amount. The motor decelerates according to its
+    /// deceleration limits, if any, before braking. Use [`Device::set_braking_now`] to ignore them.
+    ///
+    /// # Arguments
+    /// * `motor_idx` - The index of the motor, zero-indexed.
+    /// * `amount`    - How hard to brake, between 0.0 (which lets the motor coast) and 1.0 (full
+    ///   braking).
+    pub fn set_braking(&mut self, motor_idx: u8, amount: f32) -> Result {
+        let cmd = get_braking_cmd(self.controller_type, motor_idx, amount, BrakingMode::Normal)?;
         self.write_command(&cmd)
     }
 
-    pub fn clear_latched_status_flags(&mut self, flags: ClearLatchedStatusFlags) -> Result {
-        self.write_command(&flags)
+    /// Same as [`Device::set_braking`], but the braking is applied immediately, ignoring any
+    /// deceleration limits.
+    pub fn set_braking_now(&mut self, motor_idx: u8, amount: f32) -> Result {
+        let cmd = get_braking_cmd(self.controller_type, motor_idx, amount, BrakingMode::Now)?;
+        self.write_command(&cmd)
     }
 
-    /// Call this function to obtain the firmware version reported by the device.
-    pub fn firmware_version(&mut self) -> Result<FirmwareVersion> {
-        let cmd = GetFirmwareVersion;
-        self.write_command(&cmd)?;
-        let firmware_version = self.read_command(&cmd)?;
-        Ok(firmware_version)
+    /// Stops a motor and lets it spin down freely, as opposed to actively braking it. Much like
+    /// [`Device::set_braking`], this respects the motor's deceleration limits.
+    pub fn coast(&mut self, motor_idx: u8) -> Result {
+        self.set_braking(motor_idx, 0.)
     }
 
-    fn get_speed_cmd(&self, motor_idx: u8, speed: f32, mode: SpeedMode) -> Result<SetSpeed> {
+    /// Makes all motors coast immediately, ignoring any deceleration limits. This is the fastest
+    /// way to cut power to the motors.
+    pub fn coast_now(&mut self) -> Result {
+        self.write_command(&CoastNow)
+    }
+
+    /// Stops all motors as fast as possible, by making them coast immediately with a single
+    /// command (see [`Device::coast_now`]). Note that this coasts rather than brakes, so the
+    /// motors are no longer driven, but may take a moment to spin down. If you need them to stop
+    /// dead, use [`Device::hard_brake_all`] instead.
+    pub fn emergency_stop(&mut self) -> Result {
+        self.coast_now()
+    }
+
+    /// Applies a different action to each motor in one go, e.g. driving motor 0 at half speed while
+    /// braking motor 1 and letting motor 2 coast. Speeds are buffered and committed together, so
+    /// all the [`MotorAction::Speed`] actions take effect simultaneously, and the braking and
+    /// coasting actions are sent right after.
+    ///
+    /// # Arguments
+    /// * `actions` - The action to apply to each motor, in order of motor index. Much like
+    ///   [`Device::set_all_speeds`], the length must match the number of motor
+    ///   channels on your controller.
+    pub fn apply_actions(&mut self, actions: &[MotorAction]) -> Result {
         let num_motors = self.controller_type.motor_channels();
-        if speed.abs() > 1. {
-            Err(Error::InvalidSpeed(speed))
-        } else if motor_idx >= num_motors {
-            Err(Error::InvalidMotor {
-                provided: motor_idx,
-                num_motors,
-            })
-        } else {
-            let speed = (speed * 800.) as i16;
-            Ok(SetSpeed {
-                mode,
-                speed,
-                motor: motor_idx + 1,
-            })
+        if usize::from(num_motors) != actions.len() {
+            return Err(Error::IncorrectNumberSpeeds {
+                provided: actions.len().try_into().unwrap(),
+                actual: num_motors,
+            });
+        }
+
+        let mut speed_cmds = Vec::with_capacity(actions.len());
+        let mut braking_cmds = Vec::new();
+        for (motor_idx, action) in (0..num_motors).zip(actions) {
+            let speed = match action {
+                MotorAction::Speed(speed) => *speed,
+                MotorAction::Brake(amount) => {
+                    braking_cmds.push(get_braking_cmd(
+                        self.controller_type,
+                        motor_idx,
+                        *amount,
+                        BrakingMode::Normal,
+                    )?);
+                    0.
+                }
+                MotorAction::Coast => {
+                    braking_cmds.push(get_braking_cmd(
+                        self.controller_type,
+                        motor_idx,
+                        0.,
+                        BrakingMode::Normal,
+                    )?);
+                    0.
+                }
+            };
+            speed_cmds.push(self.get_speed_cmd(motor_idx, speed, SpeedMode::Buffered)?);
+        }
+
+        self.before_speed_command()?;
+        for cmd in speed_cmds {
+            self.write_command(&cmd)?;
+        }
+        self.write_command(&SetAllSpeedsUsingBuffers {
+            mode: SpeedModeNoBuffer::Normal,
+        })?;
+        for cmd in braking_cmds {
+            self.write_command(&cmd)?;
+        }
+        Ok(())
+    }
+
+    /// Applies full braking to every motor immediately, ignoring any deceleration limits that have
+    /// been configured. This is meant as a "slam the brakes" primitive, e.g. for collision
+    /// avoidance, and it is very harsh on your drivetrain, so prefer a regular speed command of 0
+    /// if you just want to come to a stop.
+    pub fn hard_brake_all(&mut self) -> Result {
+        for motor in 1..=self.controller_type.motor_channels() {
+            let cmd = SetBraking {
+                mode: BrakingMode::Now,
+                motor,
+                amount: 800,
+            };
+            self.write_command(&cmd)?;
         }
+        Ok(())
     }
 
-    fn write_protocol_options(&mut self) -> Result {
-        let cmd = SetProtocolOptions {
-            crc_for_commands: self.cmd_crc,
-            crc_for_responses: self.res_crc,
-            i2c_general_call: self.i2c_general_call,
+    /// Resets the controller's command timeout, without changing any motor speeds. If the
+    /// controller doesn't receive a valid command for longer than the command timeout, it stops
+    /// the motors, so you need to either keep sending speeds or call this regularly. It's cheaper
+    /// than re-sending speeds, as it's a single byte command (plus CRC), which makes it the
+    /// recommended way of keeping the motors going.
+    ///
+    /// The command timeout is a regular variable rather than an EEPROM setting, so it always goes
+    /// back to its default when the controller powers up, resets or reinitialises.
+    pub fn reset_command_timeout(&mut self) -> Result {
+        self.write_command(&ResetCommandTimeout)
+    }
+
+    /// Tells the controller we're still here. This is a shorter name for
+    /// [`Device::reset_command_timeout`], see its documentation for details.
+    pub fn feed(&mut self) -> Result {
+        self.reset_command_timeout()
+    }
+
+    /// Sends several commands back to back, in a single transaction. Every command is encoded (and
+    /// so validated) before any of them is sent, so an invalid command doesn't leave the rest
+    /// half-sent, and if error checking is enabled (see [`Device::set_error_checking`]), the
+    /// status flags are only checked once, after the last one. If a maximum write length is set
+    /// (see [`DeviceBuilder::max_write_len`]), it applies to all the commands together.
+    ///
+    /// ```no_run
+    /// # fn example<I: embedded_hal::i2c::I2c>(
+    /// #     device: &mut pololu_motoron::Device<I>,
+    /// # ) -> pololu_motoron::Result {
+    /// use pololu_motoron::{ClearLatchedStatusFlags, Command};
+    ///
+    /// let clear_reset = ClearLatchedStatusFlags {
+    ///     reset: true,
+    ///     ..Default::default()
+    /// };
+    /// let clear_errors = ClearLatchedStatusFlags {
+    ///     protocol_error: true,
+    ///     crc_error: true,
+    ///     ..Default::default()
+    /// };
+    /// let cmds: [Box<dyn Command<Response = ()>>; 2] =
+    ///     [Box::new(clear_reset), Box::new(clear_errors)];
+    /// device.write_commands(&cmds)
+    /// # }
+    /// ```
+    pub fn write_commands(&mut self, cmds: &[Box<dyn Command<Response = ()>>]) -> Result {
+        let mut data = Vec::new();
+        for cmd in cmds {
+            data.extend(self.encode(cmd.as_ref())?);
+        }
+        if let Some(max) = self.max_write_len {
+            if data.len() > max {
+                return Err(Error::CommandTooLong {
+                    len: data.len(),
+                    max,
+                });
+            }
+        }
+        self.write_frame(&data)?;
+        if self.error_checking {
+            self.check_protocol_errors()?;
+        }
+        Ok(())
+    }
+
+    /// Sends the same command to every device with a device number between
+    /// `starting_device_number` and `starting_device_number + device_count - 1`, in a single
+    /// transaction. This is how synchronised setups with several controllers are driven, e.g.
+    /// starting all their motors at the same time. Only the devices in the range act on the
+    /// command, so to reach all of them at once, send it on the general call address (0), for
+    /// instance with `device.with_address(0, |all| all.multi_device_write(...))`.
+    ///
+    /// # Arguments
+    /// * `starting_device_number` - The device number of the first device, between 0 and 0x7F.
+    /// * `device_count`           - How many devices to send the command to, between 0 and 0x7F.
+    /// * `command`                - The command to send, which can't be one with a response.
+    pub fn multi_device_write<C: Command<Response = ()>>(
+        &mut self,
+        starting_device_number: u8,
+        device_count: u8,
+        command: C,
+    ) -> Result {
+        self.write_command(&MultiDeviceWrite {
+            starting_device_number,
+            device_count,
+            command,
+        })
+    }
+
+    /// Checks whether any device with a device number between `starting_device_number` and
+    /// `starting_device_number + device_count - 1` has an error active, which is worth doing
+    /// before driving a chain of controllers with [`Device::multi_device_write`].
+    ///
+    /// # Arguments
+    /// * `starting_device_number` - The device number of the first device, between 0 and 0x7F.
+    /// * `device_count`           - How many devices to check, between 0 and 0x7F.
+    pub fn multi_device_error_check(
+        &mut self,
+        starting_device_number: u8,
+        device_count: u8,
+    ) -> Result<MultiDeviceErrorCheckResponse> {
+        let cmd = MultiDeviceErrorCheck {
+            starting_device_number,
+            device_count,
         };
         self.write_command(&cmd)?;
-        Ok(())
+        self.read_command(&cmd)
     }
 
-    fn write_command<C: Command>(&mut self, cmd: &C) -> Result {
-        let data = encode_command(cmd, self.cmd_crc)?;
-        self.device.write(&data[..])?;
+    /// Retargets this device to the controller at a different address on the same bus, without
+    /// reopening it. Each controller has its own protocol options, so the ones cached in this
+    /// object are written to the new controller right away to make sure they match, and anything
+    /// else cached about the old controller (such as its firmware version) is forgotten. As with
+    /// [`Device::with_address`], the new controller is assumed to be of the same
+    /// [`ControllerType`].
+    pub fn set_address(&mut self, address: SevenBitAddress) -> Result {
+        check_address(address)?;
+        self.address = address;
+        self.known_speeds = None;
+        self.firmware_version = None;
+        self.reset_latch_checked = false;
+        self.write_protocol_options()
+    }
+
+    /// Temporarily points this device at a different I2C address, runs `f` with it, and then points
+    /// it back at the original address, even if `f` returned an error. This lets you talk to
+    /// several controllers on the same bus while reusing a single open file descriptor.
+    ///
+    /// Anything cached about this device, such as its firmware version, is set aside while `f` runs
+    /// and restored afterwards. Keep in mind that everything else about this device stays the same
+    /// while `f` runs, so the other controller is assumed to be of the same [`ControllerType`] and
+    /// to have the same protocol options (such as CRC) as the ones cached for this one. If that's
+    /// not the case, either configure it accordingly or open a separate [`Device`] for it.
+    pub fn with_address<R>(
+        &mut self,
+        address: SevenBitAddress,
+        f: impl FnOnce(&mut Device<I>) -> Result<R>,
+    ) -> Result<R> {
+        check_address(address)?;
+        // Whatever we know about this device doesn't apply to the other one
+        let original_address = core::mem::replace(&mut self.address, address);
+        let known_speeds = self.known_speeds.take();
+        let firmware_version = self.firmware_version.take();
+        let reset_latch_checked = core::mem::take(&mut self.reset_latch_checked);
+        let result = f(self);
+        self.address = original_address;
+        self.known_speeds = known_speeds;
+        self.firmware_version = firmware_version;
+        self.reset_latch_checked = reset_latch_checked;
+        result
+    }
+
+    /// Coasts all motors and closes the device. Unless the device was built with
+    /// [`DeviceBuilder::coast_on_drop`], simply dropping a [`Device`] doesn't send anything to the
+    /// controller, so the motors keep going until the command timeout kicks in. Either way, use
+    /// this instead if you want your shutdown code to confirm that the motors were actually told
+    /// to stop, as any error while coasting is returned.
+    pub fn close(mut self) -> Result {
+        // We're coasting here already, no need to do it again on drop
+        self.coast_on_drop = false;
+        self.coast_now()
+    }
+
+    /// Reads the settings stored in the controller's EEPROM, such as its device number, and parses
+    /// them into an [`EepromConfig`].
+    pub fn read_eeprom_config(&mut self) -> Result<EepromConfig> {
+        let length = EEPROM_CONFIG_LEN
+            .try_into()
+            .expect("config is under 32 bytes");
+        let data = self.read_eeprom(EEPROM_CONFIG_OFFSET, length)?;
+        Ok(EepromConfig::from_bytes(&data))
+    }
+
+    /// Writes the given settings to the controller's EEPROM. The controller only loads its settings
+    /// when it starts up, so you need to reset it (see [`Device::reset`]) for them to take effect.
+    /// Only the bytes that differ from what's currently stored are written, to save on EEPROM
+    /// write cycles, which are limited.
+    pub fn write_eeprom_config(&mut self, config: &EepromConfig) -> Result {
+        let current = self.read_eeprom_config()?.to_bytes();
+        let offsets = EEPROM_CONFIG_OFFSET..;
+        for ((offset, value), current) in offsets.zip(config.to_bytes()).zip(current) {
+            if value != current {
+                self.write_eeprom(offset, value)?;
+            }
+        }
         Ok(())
     }
 
-    fn read_command<C: Command>(&mut self, cmd: &C) -> Result<C::Response> {
-        let response_len = cmd.expected_response_bytes() + if self.res_crc { 1 } else { 0 };
-        let mut data = vec![0; response_len];
-        self.device.read(&mut data[..])?;
-        let response = decode_response::<C>(data, self.res_crc)?;
-        Ok(response)
+    /// Reads `length` raw bytes from the controller's EEPROM, starting at `offset`. See the
+    /// [settings reference](https://www.pololu.com/docs/0J84) for what lives at each offset, or
+    /// use [`Device::read_eeprom_config`] to get them already decoded.
+    ///
+    /// # Arguments
+    /// * `offset` - The offset of the first byte to read, between 0 and 0x7F.
+    /// * `length` - How many bytes to read, between 1 and 32.
+    pub fn read_eeprom(&mut self, offset: u8, length: u8) -> Result<Vec<u8>> {
+        let cmd = ReadEeprom { offset, length };
+        self.write_command(&cmd)?;
+        self.read_command(&cmd)
+    }
+
+    /// Writes a single raw byte to the controller's EEPROM. As with
+    /// [`Device::write_eeprom_config`], the new value only takes effect after a reset.
+    ///
+    /// EEPROM can only be written a limited number of times (on the order of 100,000 cycles per
+    /// byte) before it wears out, so don't call this in a loop, and avoid writing values that
+    /// haven't changed.
+    ///
+    /// # Arguments
+    /// * `offset` - The offset of the byte to write, between 0 and 0x7F.
+    /// * `value`  - The value to write.
+    pub fn write_eeprom(&mut self, offset: u8, value: u8) -> Result {
+        self.write_command(&WriteEeprom { offset, value })
+    }
+
+    /// Reads the device number stored in EEPROM, which is the I2C address the controller uses
+    /// after it starts up. This can differ from the address you're talking to it on if it was
+    /// changed without resetting the controller afterwards.
+    pub fn read_device_number(&mut self) -> Result<u16> {
+        let data = self.read_eeprom(EEPROM_DEVICE_NUMBER_OFFSET, 2)?;
+        Ok(from_7bit_pair(data[0], data[1]))
+    }
+
+    /// Stores a new device number in EEPROM, which is how you give each controller in a chain its
+    /// own I2C address. The controller keeps using its current address until it's reset (see
+    /// [`Device::reset`]) or power cycled, after which you need to talk to it on the new one (see
+    /// [`Device::set_address`]). The number must be between 0 and 0x7F, or
+    /// [`CommandsError::InvalidValue`] is returned.
+    pub fn set_device_number(&mut self, new_number: u8) -> Result {
+        if new_number > 0x7F {
+            return Err(CommandsError::InvalidValue {
+                min: 0,
+                max: 0x7F,
+                value: new_number.into(),
+                field: "new_number",
+            }
+            .into());
+        }
+        self.write_eeprom(EEPROM_DEVICE_NUMBER_OFFSET, new_number)?;
+        // The high bits of the device number, which 7-bit addresses don't use
+        self.write_eeprom(EEPROM_DEVICE_NUMBER_OFFSET + 1, 0)
+    }
+
+    /// Hands this device over to a background thread that keeps the motors alive by resetting the
+    /// command timeout every `keepalive_interval`, so you only need to send new speeds when they
+    /// change. If `max_consecutive_errors` keepalives in a row fail, the supervisor coasts the
+    /// motors and reports the failure. This is meant for unattended robots that must fail safe if
+    /// the bus degrades. See [`SupervisedDevice`] for how to use the device and observe failures.
+    ///
+    /// Note that `keepalive_interval` needs to be comfortably shorter than the command timeout
+    /// configured on the controller for this to be useful.
+    pub fn supervise(
+        self,
+        keepalive_interval: Duration,
+        max_consecutive_errors: u32,
+    ) -> SupervisedDevice<I>
+    where
+        I: Send + 'static,
+    {
+        SupervisedDevice::new(self, keepalive_interval, max_consecutive_errors)
+    }
+
+    /// Clears a latched motor fault. After a motor fault, the controller refuses to drive the
+    /// motors until the fault is cleared.
+    ///
+    /// # Arguments
+    /// * `unconditional` - If false, the fault is only cleared if the condition that caused it is
+    ///   no longer present. If true, the fault is cleared regardless.
+    pub fn clear_motor_fault(&mut self, unconditional: bool) -> Result {
+        self.write_command(&ClearMotorFault { unconditional })
+    }
+
+    /// Clears the reset flag, which the controller latches after powering up or resetting. While
+    /// it's latched, the controller ignores speed commands. [`Device::reset`] and
+    /// [`Device::reinitialise`] already clear it for you.
+    pub fn clear_reset_flag(&mut self) -> Result {
+        self.clear_latched_status_flags(ClearLatchedStatusFlags {
+            reset: true,
+            ..Default::default()
+        })
+    }
+
+    pub fn clear_latched_status_flags(&mut self, flags: ClearLatchedStatusFlags) -> Result {
+        self.write_command(&flags)
+    }
+
+    /// Sets the given latched status flags, as if the conditions they represent had happened. This
+    /// is mostly useful to test how your code handles errors, without having to cause them for
+    /// real. Depending on the error response configured on the controller, setting some of these
+    /// flags may stop the motors. They can be cleared again with
+    /// [`Device::clear_latched_status_flags`].
+    pub fn set_latched_status_flags(&mut self, flags: SetLatchedStatusFlags) -> Result {
+        self.write_command(&flags)
+    }
+
+    /// Reads the controller's status flags and returns the ones that are currently set, which is
+    /// handy for logging or showing to users, e.g. `controller reports: command timeout, motor
+    /// faulting`.
+    pub fn active_flags(&mut self) -> Result<Vec<StatusFlag>> {
+        let flags = self.read_status_flags()?;
+        Ok(StatusFlag::ALL
+            .into_iter()
+            .filter(|flag| flag.is_set(flags))
+            .collect())
+    }
+
+    /// Reads the controller's status flags and decodes them into a [`MotorStatus`], so you can
+    /// check on the controller's health without masking bits yourself.
+    pub fn motor_status(&mut self) -> Result<MotorStatus> {
+        Ok(MotorStatus::from(self.read_status_flags()?))
+    }
+
+    /// Reads the raw latched status flags. These are the low 10 bits of the status flags variable,
+    /// which stay set until cleared with [`Device::clear_latched_status_flags`]:
+    ///
+    /// | Bit | Flag                                     |
+    /// |-----|------------------------------------------|
+    /// | 0   | [`StatusFlag::ProtocolError`]            |
+    /// | 1   | [`StatusFlag::CrcError`]                 |
+    /// | 2   | [`StatusFlag::CommandTimeoutLatched`]    |
+    /// | 3   | [`StatusFlag::MotorFaultLatched`]        |
+    /// | 4   | [`StatusFlag::NoPowerLatched`]           |
+    /// | 5   | [`StatusFlag::UartError`]                |
+    /// | 9   | [`StatusFlag::Reset`]                    |
+    ///
+    /// The rest of the bits are always 0.
+    pub fn get_latched_status_flags(&mut self) -> Result<u16> {
+        Ok(self.read_status_flags()? & LATCHED_STATUS_FLAGS_MASK)
+    }
+
+    /// Reads the raw current (non-latched) status flags. These are the high 6 bits of the status
+    /// flags variable, which reflect the controller's state right now:
+    ///
+    /// | Bit | Flag                                |
+    /// |-----|-------------------------------------|
+    /// | 10  | [`StatusFlag::CommandTimeout`]      |
+    /// | 11  | [`StatusFlag::MotorFaulting`]       |
+    /// | 12  | [`StatusFlag::NoPower`]             |
+    /// | 13  | [`StatusFlag::ErrorActive`]         |
+    /// | 14  | [`StatusFlag::MotorOutputEnabled`]  |
+    /// | 15  | [`StatusFlag::MotorDriving`]        |
+    ///
+    /// The rest of the bits are always 0.
+    pub fn get_current_status_flags(&mut self) -> Result<u16> {
+        Ok(self.read_status_flags()? & !LATCHED_STATUS_FLAGS_MASK)
+    }
+
+    /// Returns the number of distinct speeds a motor can be set to. Speeds get sent to the
+    /// controller as an integer between -800 and 800 by default (see
+    /// [`Device::set_max_speed_scale`]), so there are 1601 of them, and any change smaller than a
+    /// step (1/800) may not change the speed at all. See also [`Device::quantize_speed`].
+    pub fn speed_resolution_steps(&self) -> u16 {
+        2 * self.max_speed.unsigned_abs() + 1
+    }
+
+    /// Enables or disables checking for errors after every command. The controller silently ignores
+    /// malformed commands (or ones with the wrong CRC), only setting a status flag. With this
+    /// enabled, the status flags are read after each command without a response, and if the
+    /// controller flagged a protocol or CRC error, [`Error::ProtocolError`] is returned and those
+    /// flags are cleared. This adds a read to every command, so it's disabled by default.
+    pub fn set_error_checking(&mut self, enabled: bool) {
+        self.error_checking = enabled;
+    }
+
+    /// Returns how many motors this controller has. Same as
+    /// [`ControllerType::motor_channels`].
+    pub fn motor_count(&self) -> u8 {
+        self.controller_type.motor_channels()
+    }
+
+    /// Returns an iterator over the indices of every motor on this controller, which is handy to
+    /// loop over all of them.
+    pub fn motor_indices(&self) -> impl Iterator<Item = u8> {
+        0..self.motor_count()
+    }
+
+    /// Sets how many times a failed I2C write or read is retried before its error is returned. On
+    /// noisy buses (say, next to motors generating a lot of EMI), errors like `EREMOTEIO` or
+    /// `EBUSY` are often transient, and retrying after a short backoff succeeds. The backoff
+    /// starts at 1 ms and doubles on every retry. Defaults to zero (no retries).
+    pub fn set_retries(&mut self, count: u8) {
+        self.retries = count;
+    }
+
+    /// Sets the raw speed that a speed of 1.0 gets sent to the controller as, which defaults to
+    /// 800, the fastest a motor can go. If your motors are limited to a lower speed on the
+    /// controller, setting this to that limit makes the full `[-1.0, 1.0]` range usable instead of
+    /// saturating past it. The value is clamped between 1 and 800.
+    pub fn set_max_speed_scale(&mut self, max: i16) {
+        self.max_speed = max.clamp(1, 800);
+    }
+
+    /// Snaps a speed in the `[-1.0, 1.0]` range to the closest speed that can actually be sent to
+    /// the controller. Note that speeds are truncated towards zero when they are converted to the
+    /// controller's raw steps, and this mirrors that, so `quantize_speed(x)` is exactly the speed a
+    /// motor ends up with after `set_speed(motor, x)`.
+    pub fn quantize_speed(&self, speed: f32) -> f32 {
+        f32::from(speed_to_raw(speed, self.max_speed)) / f32::from(self.max_speed)
+    }
+
+    /// Reads a snapshot of the controller's state: its status flags, input voltage, and the speed
+    /// and current (if the controller can sense current) of each motor. This is meant to be
+    /// called once per control loop iteration, so it reads everything in as few transactions as
+    /// possible: one for the general variables and one per motor.
+    pub fn telemetry(&mut self) -> Result<Telemetry> {
+        let vin_idx = usize::from(VIN_VOLTAGE_OFFSET - STATUS_FLAGS_OFFSET);
+        let general = self.read_variables(0, STATUS_FLAGS_OFFSET, vin_idx + 2)?;
+        let current_sensing = self.controller_type.is_current_sensing();
+        let current_idx = usize::from(CURRENT_SENSE_PROCESSED_OFFSET - CURRENT_SPEED_OFFSET);
+        let motor_len = if current_sensing { current_idx + 2 } else { 2 };
+
+        let mut motors = Vec::new();
+        for motor_idx in 0..self.controller_type.motor_channels() {
+            let data = self.read_variables(motor_idx + 1, CURRENT_SPEED_OFFSET, motor_len)?;
+            motors.push(MotorTelemetry {
+                speed: self.raw_to_speed(motor_idx, i16::from_le_bytes([data[0], data[1]])),
+                current: current_sensing
+                    .then(|| u16::from_le_bytes([data[current_idx], data[current_idx + 1]])),
+            });
+        }
+
+        Ok(Telemetry {
+            status_flags: u16::from_le_bytes([general[0], general[1]]),
+            input_voltage: self
+                .raw_to_volts(u16::from_le_bytes([general[vin_idx], general[vin_idx + 1]])),
+            motors,
+        })
+    }
+
+    /// Starts recording every command written to the device into `w`, replacing any recording
+    /// already in progress. Each command goes on its own line, as the number of microseconds since
+    /// the recording started followed by the bytes of the encoded command (including the CRC
+    /// byte, if enabled) in hex, for example `1520 e1 10 03 70 7c`. This is useful to capture the
+    /// traffic of a session to diff against a known-good one, or to replay it later. Errors
+    /// writing to `w` are logged, but otherwise don't affect the commands being sent.
+    pub fn start_recording<W: std::io::Write + Send + 'static>(&mut self, w: W) {
+        self.recording = Some((Instant::now(), Box::new(w)));
+    }
+
+    /// Stops the recording started with [`Device::start_recording`], flushing the writer. Does
+    /// nothing if nothing is being recorded.
+    pub fn stop_recording(&mut self) -> std::io::Result<()> {
+        match self.recording.take() {
+            Some((_, mut w)) => w.flush(),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the raw bytes of the most recent response read from the device, including the CRC
+    /// byte if response CRCs are enabled, or `None` if nothing has been read yet. The bytes are
+    /// recorded before being decoded, so this is useful to see exactly what came off the bus when
+    /// a read fails with a length or CRC error.
+    pub fn last_response_bytes(&self) -> Option<&[u8]> {
+        self.last_response.as_deref()
+    }
+
+    /// Call this function to obtain the firmware version reported by the device. The version can't
+    /// change while the device is running, so it's only read from the device the first time, after
+    /// which it's cached. See [`Device::refresh_firmware_version`] to read it again anyway.
+    pub fn firmware_version(&mut self) -> Result<FirmwareVersion> {
+        match self.firmware_version {
+            Some(version) => Ok(version),
+            None => self.refresh_firmware_version(),
+        }
+    }
+
+    /// Same as [`Device::firmware_version`], but always reads the version from the device,
+    /// updating the cached one.
+    pub fn refresh_firmware_version(&mut self) -> Result<FirmwareVersion> {
+        let cmd = GetFirmwareVersion;
+        self.write_command(&cmd)?;
+        let firmware_version = self.read_command(&cmd)?;
+        self.firmware_version = Some(firmware_version);
+        Ok(firmware_version)
+    }
+
+    /// Returns whether this device supports the given feature. This takes into account both the
+    /// hardware of the controller and the version of the firmware it's running, as some features
+    /// may need a newer firmware. The firmware version is only read from the device the first time
+    /// it's needed, after which it's cached.
+    pub fn firmware_supports(&mut self, feature: Feature) -> Result<bool> {
+        if !feature.supported_by(self.controller_type) {
+            return Ok(false);
+        }
+        let version = self.firmware_version()?;
+        Ok((version.major_fw_version, version.minor_fw_version) >= feature.min_firmware_version())
+    }
+
+    /// Checks that the connected controller is the [`ControllerType`] this device was opened with,
+    /// by reading the product ID from its firmware version, and returns
+    /// [`Error::ControllerMismatch`] if it isn't. This catches passing the wrong controller type
+    /// when opening the device, which would otherwise go unnoticed until the wrong number of motor
+    /// channels gets used. Controllers that only differ in form factor can't be told apart (see
+    /// [`ControllerType::product`]), and if the product ID isn't a known one, we log a warning and
+    /// assume the controller type is correct. This is never checked automatically.
+    pub fn verify_controller_type(&mut self) -> Result {
+        let version = self.firmware_version()?;
+        let Some(actual) = version.controller_type() else {
+            log::warn!(
+                "Unknown product ID {:#06x}, can't verify the controller type",
+                version.product_id
+            );
+            return Ok(());
+        };
+        if actual.product() != self.controller_type.product() {
+            return Err(Error::ControllerMismatch {
+                expected: self.controller_type,
+                actual,
+            });
+        }
+        Ok(())
+    }
+
+    /// Returns the number of motor channels of the connected controller, detected from the product
+    /// ID in its firmware version (see [`ControllerType::from_product_id`]) rather than from the
+    /// [`ControllerType`] this device was opened with. If the product ID isn't a known one, we log
+    /// a warning and fall back to the channel count of the controller type this device was opened
+    /// with.
+    pub fn detected_channel_count(&mut self) -> Result<u8> {
+        let version = self.firmware_version()?;
+        match version.controller_type() {
+            Some(controller_type) => Ok(controller_type.motor_channels()),
+            None => {
+                log::warn!(
+                    "Unknown product ID {:#06x}, assuming {} motor channels",
+                    version.product_id,
+                    self.controller_type.motor_channels()
+                );
+                Ok(self.controller_type.motor_channels())
+            }
+        }
+    }
+
+    /// Checks that the connected controller has as many motor channels as the [`ControllerType`]
+    /// this device was opened with, returning [`Error::ChannelCountMismatch`] if it doesn't. See
+    /// [`Device::detected_channel_count`] for how the channel count is detected. Unlike
+    /// [`Device::verify_controller_type`], this accepts a different controller as long as it has
+    /// the same number of channels.
+    pub fn verify_channel_count(&mut self) -> Result {
+        let expected = self.controller_type.motor_channels();
+        let actual = self.detected_channel_count()?;
+        if actual != expected {
+            return Err(Error::ChannelCountMismatch { expected, actual });
+        }
+        Ok(())
+    }
+
+    /// Reads every per-motor variable of a motor and returns the raw bytes, where the byte at index
+    /// `N` is the variable byte at offset `N`, as described in the
+    /// [variable reference](https://www.pololu.com/docs/0J84). This is mostly useful for
+    /// diagnostics, such as snapshotting the complete live state of a controller for a bug report.
+    ///
+    /// The per-motor variable space is 35 bytes long, which is more than a single `GetVariables`
+    /// command can return, so this takes two transactions on the bus.
+    ///
+    /// # Arguments
+    /// * `motor_idx` - The index of the motor, zero-indexed.
+    pub fn dump_all_variables(&mut self, motor_idx: u8) -> Result<Vec<u8>> {
+        self.check_motor_idx(motor_idx)?;
+        self.read_variables(motor_idx + 1, 0, MOTOR_VARIABLES_LEN)
+    }
+
+    /// Same as [`Device::dump_all_variables`], but for the general (device-wide) variables, such
+    /// as the status flags and input voltage. The general variable space is 12 bytes long, so this
+    /// takes a single transaction.
+    pub fn dump_device_variables(&mut self) -> Result<Vec<u8>> {
+        self.read_variables(0, 0, GENERAL_VARIABLES_LEN)
+    }
+
+    /// Reads `length` raw bytes of variables starting at `offset`, in a single `GetVariables`
+    /// command. Multi-byte variables are little-endian. See the
+    /// [variable reference](https://www.pololu.com/docs/0J84) for what lives at each offset.
+    ///
+    /// # Arguments
+    /// * `motor`  - Which variable space to read from. 0 means the general (device-wide)
+    ///   variables, while 1 and up are the per-motor variables of each motor, so they
+    ///   are one-indexed, unlike the rest of this crate's motor indices.
+    /// * `offset` - The offset of the first byte to read, between 0 and 0x7F.
+    /// * `length` - How many bytes to read, between 1 and 32.
+    pub fn get_variables(&mut self, motor: u8, offset: u8, length: u8) -> Result<Vec<u8>> {
+        let cmd = GetVariables {
+            motor,
+            offset,
+            length,
+        };
+        self.write_command(&cmd)?;
+        self.read_command(&cmd)
+    }
+
+    /// Writes a raw value to a variable, with a `SetVariable` command. The value must fit in 14
+    /// bits (0 to 0x3FFF). See [`Device::set_variable_named`] if you don't want to look up the
+    /// offsets yourself.
+    ///
+    /// # Arguments
+    /// * `motor`  - Which variable space to write to. 0 means the general (device-wide)
+    ///   variables, while 1 and up are the per-motor variables of each motor, as with
+    ///   [`Device::get_variables`].
+    /// * `offset` - The offset of the variable, between 0 and 0x7F.
+    /// * `value`  - The value to write.
+    pub fn set_variable(&mut self, motor: u8, offset: u8, value: u16) -> Result {
+        self.write_command(&SetVariable {
+            motor,
+            offset,
+            value,
+        })
+    }
+
+    /// Same as [`Device::set_variable`], but takes a named [`Variable`] instead of a raw offset.
+    /// General variables (see [`Variable::is_general`]) must be written with `motor` set to 0.
+    pub fn set_variable_named(&mut self, motor: u8, var: Variable, value: u16) -> Result {
+        self.set_variable(motor, var.offset(), value)
+    }
+
+    /// Reads a named [`Variable`], taking care of its offset and length. General variables (see
+    /// [`Variable::is_general`]) must be read with `motor` set to 0, while per-motor ones use 1
+    /// and up, as with [`Device::get_variables`].
+    pub fn get_variable_typed(&mut self, motor: u8, var: Variable) -> Result<u16> {
+        let data = self.read_variables(motor, var.offset(), usize::from(var.byte_len()))?;
+        Ok(match data[..] {
+            [low, high] => u16::from_le_bytes([low, high]),
+            [value] => u16::from(value),
+            _ => unreachable!("variables are one or two bytes long"),
+        })
+    }
+
+    /// Same as [`Device::set_variable_named`], but also checks that the value fits in the
+    /// variable, so values too big for one-byte variables return [`CommandsError::InvalidValue`]
+    /// rather than getting truncated by the controller.
+    pub fn set_variable_typed(&mut self, motor: u8, var: Variable, value: u16) -> Result {
+        let max: u16 = if var.byte_len() == 1 { 0xFF } else { 0x3FFF };
+        if value > max {
+            return Err(CommandsError::InvalidValue {
+                min: 0,
+                max: max.into(),
+                value: value.into(),
+                field: "value",
+            }
+            .into());
+        }
+        self.set_variable_named(motor, var, value)
+    }
+
+    /// Sets the maximum acceleration of a motor, in both directions. This is how much the speed
+    /// can increase by in each update period, in the controller's raw speed units (-800 to 800),
+    /// so smaller values make the motor ramp up more gently, and 0 disables the limit. Limiting
+    /// acceleration avoids current spikes when a heavy load suddenly starts or changes direction.
+    /// Values above the 14 bits the controller accepts are clamped.
+    ///
+    /// # Arguments
+    /// * `motor_idx` - The index of the motor, zero-indexed.
+    /// * `accel`     - The maximum acceleration.
+    pub fn set_max_acceleration(&mut self, motor_idx: u8, accel: u16) -> Result {
+        self.set_limit_both_directions(
+            motor_idx,
+            Variable::MaxAccelerationForward,
+            Variable::MaxAccelerationReverse,
+            accel,
+        )
+    }
+
+    /// Same as [`Device::set_max_acceleration`], but for how much the speed can decrease by in
+    /// each update period.
+    ///
+    /// # Arguments
+    /// * `motor_idx` - The index of the motor, zero-indexed.
+    /// * `decel`     - The maximum deceleration.
+    pub fn set_max_deceleration(&mut self, motor_idx: u8, decel: u16) -> Result {
+        self.set_limit_both_directions(
+            motor_idx,
+            Variable::MaxDecelerationForward,
+            Variable::MaxDecelerationReverse,
+            decel,
+        )
+    }
+
+    /// Reads back the maximum acceleration of a motor, as set with
+    /// [`Device::set_max_acceleration`]. The controller stores separate limits for each
+    /// direction, and this returns the forward one, which is the same as the reverse one unless
+    /// they were set separately with [`Device::set_variable_named`].
+    ///
+    /// # Arguments
+    /// * `motor_idx` - The index of the motor, zero-indexed.
+    pub fn get_max_acceleration(&mut self, motor_idx: u8) -> Result<u16> {
+        self.check_motor_idx(motor_idx)?;
+        self.get_variable_typed(motor_idx + 1, Variable::MaxAccelerationForward)
+    }
+
+    /// Same as [`Device::get_max_acceleration`], but for the maximum deceleration.
+    ///
+    /// # Arguments
+    /// * `motor_idx` - The index of the motor, zero-indexed.
+    pub fn get_max_deceleration(&mut self, motor_idx: u8) -> Result<u16> {
+        self.check_motor_idx(motor_idx)?;
+        self.get_variable_typed(motor_idx + 1, Variable::MaxDecelerationForward)
+    }
+
+    /// Sets the starting speed of a motor, for each direction. Whenever the motor starts moving from
+    /// a stop, it jumps straight to this speed (in the controller's raw speed units, 0 to 800)
+    /// instead of ramping up from zero, which helps geared motors that would otherwise stall at
+    /// low speeds because of static friction. Both values must fit in 14 bits (0 to 0x3FFF), or
+    /// [`CommandsError::InvalidValue`] is returned without writing either of them.
+    ///
+    /// # Arguments
+    /// * `motor_idx` - The index of the motor, zero-indexed.
+    /// * `forward`   - The starting speed when driving forward.
+    /// * `reverse`   - The starting speed when driving in reverse.
+    pub fn set_starting_speed(&mut self, motor_idx: u8, forward: u16, reverse: u16) -> Result {
+        self.check_motor_idx(motor_idx)?;
+        for (value, field) in [(forward, "forward"), (reverse, "reverse")] {
+            if value > 0x3FFF {
+                return Err(CommandsError::InvalidValue {
+                    min: 0,
+                    max: 0x3FFF,
+                    value: value.into(),
+                    field,
+                }
+                .into());
+            }
+        }
+        self.set_variable_named(motor_idx + 1, Variable::StartingSpeedForward, forward)?;
+        self.set_variable_named(motor_idx + 1, Variable::StartingSpeedReverse, reverse)
+    }
+
+    /// Sets the current limit of a motor, as the raw value of the current limit variable. See the
+    /// [variable reference](https://www.pololu.com/docs/0J84) for how to calculate it from a limit
+    /// in milliamps, as it depends on the controller and its current sense offset. Only controllers
+    /// that can limit current support this (see [`Device::firmware_supports`]), and
+    /// [`Error::UnsupportedFeature`] is returned on the rest.
+    ///
+    /// # Arguments
+    /// * `motor_idx` - The index of the motor, zero-indexed.
+    /// * `limit`     - The raw current limit, which must fit in 14 bits (0 to 0x3FFF).
+    pub fn set_current_limit(&mut self, motor_idx: u8, limit: u16) -> Result {
+        self.check_feature(Feature::CurrentLimiting)?;
+        self.check_motor_idx(motor_idx)?;
+        self.set_variable_named(motor_idx + 1, Variable::CurrentLimit, limit)
+    }
+
+    /// Reads the current going through a motor, in milliamps. This reads the processed current
+    /// sense variable and scales it as described in [`ControllerType::current_sense_scale`], using
+    /// the logic voltage set with [`DeviceBuilder::reference_mv`]. Only controllers that can sense
+    /// current support this (see [`Device::firmware_supports`]), and
+    /// [`Error::UnsupportedFeature`] is returned on the rest.
+    ///
+    /// # Arguments
+    /// * `motor_idx` - The index of the motor, zero-indexed.
+    pub fn read_current(&mut self, motor_idx: u8) -> Result<u16> {
+        self.check_feature(Feature::CurrentSensing)?;
+        self.check_motor_idx(motor_idx)?;
+        let scale = self
+            .controller_type
+            .current_sense_scale()
+            .expect("current sensing controllers have a scale");
+        let data = self.read_variables(motor_idx + 1, CURRENT_SENSE_PROCESSED_OFFSET, 2)?;
+        let raw = u16::from_le_bytes([data[0], data[1]]);
+        Ok((f32::from(raw) * f32::from(self.reference_mv) / 1024. * scale) as u16)
+    }
+
+    /// Reads the controller's input (VIN) voltage, in volts. This is useful for battery-powered
+    /// robots to stop before the battery gets too low. The raw reading is scaled as described in
+    /// [`ControllerType::vin_sense_scale`], using the logic voltage set with
+    /// [`DeviceBuilder::reference_mv`].
+    pub fn read_input_voltage(&mut self) -> Result<f32> {
+        let data = self.read_variables(0, VIN_VOLTAGE_OFFSET, 2)?;
+        Ok(self.raw_to_volts(u16::from_le_bytes([data[0], data[1]])))
+    }
+
+    /// Returns whether the command timeout has expired since the flag was last cleared, meaning
+    /// the controller went too long without receiving a valid command and stopped the motors.
+    /// This reads the latched flag, so it stays set until cleared with
+    /// [`Device::clear_latched_status_flags`].
+    pub fn command_timeout_occurred(&mut self) -> Result<bool> {
+        Ok(StatusFlag::CommandTimeoutLatched.is_set(self.read_status_flags()?))
+    }
+
+    /// Returns whether the motor driver is currently reporting a fault. Note the controller only
+    /// reports faults for all its motors together, so this returns the same for every motor on
+    /// the controller.
+    ///
+    /// # Arguments
+    /// * `motor_idx` - The index of the motor, zero-indexed.
+    pub fn motor_faulting(&mut self, motor_idx: u8) -> Result<bool> {
+        self.check_motor_idx(motor_idx)?;
+        Ok(StatusFlag::MotorFaulting.is_set(self.read_status_flags()?))
+    }
+
+    /// Runs the checks configured for speed commands, right before sending one. That is, checking
+    /// the reset flag isn't latched and sleeping for as long as needed to respect the minimum
+    /// interval between speed commands.
+    fn before_speed_command(&mut self) -> Result {
+        if self.guard_reset_latch && !self.reset_latch_checked {
+            if StatusFlag::Reset.is_set(self.read_status_flags()?) {
+                return Err(Error::ResetLatchActive);
+            }
+            self.reset_latch_checked = true;
+        }
+
+        if self.min_command_interval.is_zero() {
+            return Ok(());
+        }
+        if let Some(last_speed_command) = self.last_speed_command {
+            let elapsed = last_speed_command.elapsed();
+            if elapsed < self.min_command_interval {
+                std::thread::sleep(self.min_command_interval - elapsed);
+            }
+        }
+        self.last_speed_command = Some(Instant::now());
+        Ok(())
+    }
+
+    fn read_status_flags(&mut self) -> Result<u16> {
+        let data = self.read_variables(0, STATUS_FLAGS_OFFSET, 2)?;
+        Ok(u16::from(data[0]) | (u16::from(data[1]) << 8))
+    }
+
+    fn set_limit_both_directions(
+        &mut self,
+        motor_idx: u8,
+        forward: Variable,
+        reverse: Variable,
+        value: u16,
+    ) -> Result {
+        self.check_motor_idx(motor_idx)?;
+        let value = value.min(0x3FFF);
+        self.set_variable_named(motor_idx + 1, forward, value)?;
+        self.set_variable_named(motor_idx + 1, reverse, value)
+    }
+
+    fn check_feature(&mut self, feature: Feature) -> Result {
+        if self.firmware_supports(feature)? {
+            Ok(())
+        } else {
+            Err(Error::UnsupportedFeature(feature))
+        }
+    }
+
+    fn check_motor_idx(&self, motor_idx: u8) -> Result {
+        let num_motors = self.controller_type.motor_channels();
+        if motor_idx >= num_motors {
+            Err(Error::InvalidMotor {
+                provided: motor_idx,
+                num_motors,
+            })
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Reads `length` bytes of variables starting at `offset`, splitting it into as many
+    /// `GetVariables` commands as needed to stay under the limit of a single response.
+    fn read_variables(&mut self, motor: u8, offset: u8, length: usize) -> Result<Vec<u8>> {
+        let mut data = Vec::with_capacity(length);
+        while data.len() < length {
+            let chunk_len = (length - data.len()).min(MAX_VARIABLES_READ);
+            let cmd = GetVariables {
+                motor,
+                offset: offset + u8::try_from(data.len()).expect("variable space is under 0x80"),
+                length: chunk_len.try_into().expect("chunk length is at most 32"),
+            };
+            self.write_command(&cmd)?;
+            data.extend(self.read_command(&cmd)?);
+        }
+        Ok(data)
+    }
+
+    fn get_raw_speeds(&self, speeds: &[f32]) -> Result<Vec<i16>> {
+        // Check the speeds before inverting them, so errors report the speed that was passed in
+        for speed in speeds {
+            check_speed(*speed)?;
+        }
+        let speeds: Vec<f32> = speeds
+            .iter()
+            .enumerate()
+            .map(|(idx, speed)| match self.inverted.get(idx) {
+                Some(true) => -speed,
+                _ => *speed,
+            })
+            .collect();
+        get_raw_speeds(self.controller_type, self.max_speed, &speeds)
+    }
+
+    fn get_speed_cmd(&self, motor_idx: u8, speed: f32, mode: SpeedMode) -> Result<SetSpeed> {
+        check_speed(speed)?;
+        let speed = match self.inverted.get(usize::from(motor_idx)) {
+            Some(true) => -speed,
+            _ => speed,
+        };
+        get_speed_cmd(self.controller_type, self.max_speed, motor_idx, speed, mode)
+    }
+
+    /// Converts a raw speed read back from the controller into a speed between -1.0 and 1.0,
+    /// undoing the inversion of the motor if it's inverted.
+    fn raw_to_speed(&self, motor_idx: u8, raw: i16) -> f32 {
+        let speed = f32::from(raw) / f32::from(self.max_speed);
+        if self.inverted[usize::from(motor_idx)] {
+            -speed
+        } else {
+            speed
+        }
+    }
+
+    /// Converts a raw input voltage reading into volts, as described in
+    /// [`ControllerType::vin_sense_scale`].
+    fn raw_to_volts(&self, raw: u16) -> f32 {
+        f32::from(raw) * f32::from(self.reference_mv) / 1024. / 1000.
+            * self.controller_type.vin_sense_scale()
+    }
+
+    fn write_protocol_options(&mut self) -> Result {
+        let cmd = SetProtocolOptions {
+            crc_for_commands: self.cmd_crc,
+            crc_for_responses: self.res_crc,
+            i2c_general_call: self.i2c_general_call,
+        };
+        self.write_command(&cmd)?;
+        Ok(())
+    }
+
+    fn write_command<C: Command>(&mut self, cmd: &C) -> Result {
+        let data = self.encode(cmd)?;
+        self.write_frame(&data)?;
+        if self.error_checking && cmd.expected_response_bytes() == 0 {
+            self.check_protocol_errors()?;
+        }
+        Ok(())
+    }
+
+    /// Writes the reset command and forgets anything cached about the device. This doesn't check
+    /// for protocol errors afterwards, even with error checking enabled, since the device can't
+    /// answer while it's rebooting.
+    fn write_reset(&mut self) -> Result {
+        let data = self.encode(&Reset)?;
+        self.write_frame(&data)?;
+        self.known_speeds = None;
+        self.firmware_version = None;
+        Ok(())
+    }
+
+    /// Reads the status flags, returning an error (and clearing it) if the controller flagged a
+    /// protocol or CRC error. This bypasses `write_command` so it doesn't recurse.
+    fn check_protocol_errors(&mut self) -> Result {
+        let cmd = GetVariables {
+            motor: 0,
+            offset: STATUS_FLAGS_OFFSET,
+            length: 2,
+        };
+        let data = self.encode(&cmd)?;
+        self.write_frame(&data)?;
+        let flags = self.read_command(&cmd)?;
+        let flags = u16::from_le_bytes([flags[0], flags[1]]);
+        if StatusFlag::ProtocolError.is_set(flags) || StatusFlag::CrcError.is_set(flags) {
+            let data = self.encode(&ClearLatchedStatusFlags {
+                protocol_error: true,
+                crc_error: true,
+                ..Default::default()
+            })?;
+            self.write_frame(&data)?;
+            return Err(Error::ProtocolError(flags));
+        }
+        Ok(())
+    }
+
+    /// Encodes a command, checking that it can be written in a single transaction.
+    fn encode<C: Command + ?Sized>(&self, cmd: &C) -> Result<Vec<u8>> {
+        let data = encode_command(cmd, self.cmd_crc)?;
+        if let Some(max) = self.max_write_len {
+            if data.len() > max {
+                return Err(Error::CommandTooLong {
+                    len: data.len(),
+                    max,
+                });
+            }
+        }
+        Ok(data)
+    }
+
+    fn write_frame(&mut self, data: &[u8]) -> Result {
+        log::trace!("Writing command: {data:?}");
+        if let Some((start, w)) = &mut self.recording {
+            if let Err(e) = record_frame(w, start.elapsed(), data) {
+                log::warn!("Failed to record command: {e}");
+            }
+        }
+        self.with_retries(|device, address| device.write(address, data))
+    }
+
+    fn read_command<C: Command>(&mut self, cmd: &C) -> Result<C::Response> {
+        let response_len = cmd.expected_response_bytes() + if self.res_crc { 1 } else { 0 };
+        let mut reads = 0;
+        let mut attempts = 0;
+        let mut empty_read_retried = false;
+        loop {
+            reads += 1;
+            if reads > 1 {
+                // The device only sends a response once per command, so reading again would just
+                // return stale data. Ask for the response again instead.
+                let data = self.encode(cmd)?;
+                self.write_frame(&data)?;
+            }
+            if !self.response_delay.is_zero() {
+                std::thread::sleep(self.response_delay);
+            }
+            let mut data = vec![EMPTY_READ_FILL; response_len];
+            self.with_retries(|device, address| device.read(address, &mut data[..]))?;
+            log::trace!("Read response: {data:?}");
+            self.last_response = Some(data.clone());
+            if self.retry_empty_reads && data.iter().all(|&byte| byte == EMPTY_READ_FILL) {
+                if empty_read_retried {
+                    return Err(CommandsError::InvalidResponseLength {
+                        expected: response_len,
+                        actual: 0,
+                    }
+                    .into());
+                }
+                log::debug!("Got an empty response, asking for it again");
+                empty_read_retried = true;
+                continue;
+            }
+            attempts += 1;
+            match decode_response::<C>(data, self.res_crc) {
+                Err(source @ CommandsError::InvalidResponseCrc { .. })
+                    if self.crc_mismatch_retries > 0 =>
+                {
+                    if attempts > u16::from(self.crc_mismatch_retries) {
+                        return Err(Error::CrcRetriesExhausted { attempts, source });
+                    }
+                }
+                response => return Ok(response?),
+            }
+        }
+    }
+
+    /// Runs an I2C transfer, retrying it up to `self.retries` times if it fails.
+    fn with_retries(
+        &mut self,
+        mut transfer: impl FnMut(&mut I, SevenBitAddress) -> core::result::Result<(), I::Error>,
+    ) -> Result {
+        let mut backoff = RETRY_BACKOFF;
+        let mut retries_left = self.retries;
+        loop {
+            match transfer(&mut self.device, self.address) {
+                Ok(()) => return Ok(()),
+                Err(e) if retries_left > 0 => {
+                    log::debug!("Retrying I2C transfer after error: {e:?}");
+                    retries_left -= 1;
+                    std::thread::sleep(backoff);
+                    backoff *= 2;
+                }
+                Err(e) => return Err(I2cError::new(e).into()),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+fn check_address(address: SevenBitAddress) -> Result {
+    if address > 0x7F {
+        Err(Error::InvalidAddress(address))
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+fn record_frame(
+    w: &mut impl std::io::Write,
+    elapsed: Duration,
+    data: &[u8],
+) -> std::io::Result<()> {
+    write!(w, "{}", elapsed.as_micros())?;
+    for byte in data {
+        write!(w, " {byte:02x}")?;
+    }
+    writeln!(w)
+}
+
+#[cfg(any(feature = "std", feature = "async"))]
+pub(crate) fn get_raw_speeds(
+    controller_type: ControllerType,
+    max_speed: i16,
+    speeds: &[f32],
+) -> Result<Vec<i16>> {
+    let num_motors = controller_type.motor_channels();
+    if usize::from(num_motors) != speeds.len() {
+        return Err(Error::IncorrectNumberSpeeds {
+            provided: speeds.len().try_into().unwrap(),
+            actual: num_motors,
+        });
+    }
+    speeds
+        .iter()
+        .map(|speed| {
+            check_speed(*speed)?;
+            Ok(speed_to_raw(*speed, max_speed))
+        })
+        .collect()
+}
+
+#[cfg(any(feature = "std", feature = "async"))]
+pub(crate) fn get_speed_cmd(
+    controller_type: ControllerType,
+    max_speed: i16,
+    motor_idx: u8,
+    speed: f32,
+    mode: SpeedMode,
+) -> Result<SetSpeed> {
+    check_speed(speed)?;
+    let num_motors = controller_type.motor_channels();
+    if motor_idx >= num_motors {
+        Err(Error::InvalidMotor {
+            provided: motor_idx,
+            num_motors,
+        })
+    } else {
+        let speed = speed_to_raw(speed, max_speed);
+        Ok(SetSpeed {
+            mode,
+            speed,
+            motor: motor_idx + 1,
+        })
+    }
+}
+
+#[cfg(any(feature = "std", feature = "async"))]
+pub(crate) fn get_braking_cmd(
+    controller_type: ControllerType,
+    motor_idx: u8,
+    amount: f32,
+    mode: BrakingMode,
+) -> Result<SetBraking> {
+    let num_motors = controller_type.motor_channels();
+    if motor_idx >= num_motors {
+        return Err(Error::InvalidMotor {
+            provided: motor_idx,
+            num_motors,
+        });
+    }
+    if !(0. ..=1.).contains(&amount) {
+        return Err(Error::InvalidBrakingAmount(amount));
+    }
+    Ok(SetBraking {
+        mode,
+        motor: motor_idx + 1,
+        amount: (amount * 800.) as u16,
+    })
+}
+
+/// Returns [`Error::InvalidSpeed`] if the speed is outside of the `[-1.0, 1.0]` range, or NaN.
+#[cfg(any(feature = "std", feature = "async"))]
+fn check_speed(speed: f32) -> Result {
+    if (-1. ..=1.).contains(&speed) {
+        Ok(())
+    } else {
+        Err(Error::InvalidSpeed(speed))
+    }
+}
+
+/// Clamps a speed to the `[-1.0, 1.0]` range, leaving non-finite speeds alone so they still get
+/// rejected.
+#[cfg(feature = "std")]
+fn clamp_speed(speed: f32) -> f32 {
+    if speed.is_finite() {
+        speed.clamp(-1., 1.)
+    } else {
+        speed
+    }
+}
+
+#[cfg(any(feature = "std", feature = "async"))]
+fn speed_to_raw(speed: f32, max_speed: i16) -> i16 {
+    (speed * f32::from(max_speed)) as i16
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::commands::get_crc;
+    use crate::mock::MockI2c;
+    use embedded_hal::i2c::NoAcknowledgeSource;
+
+    /// Opens a device on a fresh mock bus, forgetting the writes done while opening it.
+    fn open_device(controller_type: ControllerType) -> (Device<MockI2c>, MockI2c) {
+        let mock = MockI2c::new();
+        let device = Device::new(controller_type, mock.clone(), 0x10).unwrap();
+        mock.take_writes();
+        (device, mock)
+    }
+
+    /// Appends the CRC byte to a command.
+    fn frame(data: &[u8]) -> Vec<u8> {
+        let mut data = data.to_vec();
+        data.push(get_crc(&data));
+        data
+    }
+
+    #[test]
+    fn new_writes_protocol_options() -> Result {
+        let mock = MockI2c::new();
+        Device::new(ControllerType::M2T256, mock.clone(), 0x10)?;
+        assert_eq!(mock.writes(), [frame(&[0x8B, 0x07, 0x78])]);
+        Ok(())
+    }
+
+    #[test]
+    fn set_all_speeds_writes_frame() -> Result {
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        device.set_all_speeds(&[0.5, -0.5])?;
+        assert_eq!(mock.writes(), [frame(&[0xE1, 0x10, 0x03, 0x70, 0x7C])]);
+        Ok(())
+    }
+
+    #[test]
+    fn firmware_version_parses_response() -> Result {
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        mock.queue_response_with_crc(&[0xCC, 0x00, 0x02, 0x01]);
+        let version = device.firmware_version()?;
+        assert_eq!(mock.writes(), [frame(&[0x87])]);
+        assert_eq!(version.product_id, 0xCC);
+        assert_eq!(version.minor_fw_version, 2);
+        assert_eq!(version.major_fw_version, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn firmware_supports_checks_hardware_first() -> Result {
+        // The hardware can't sense current, so there's no need to read the firmware version
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        assert!(!device.firmware_supports(Feature::CurrentSensing)?);
+        assert!(mock.writes().is_empty());
+
+        let (mut device, mock) = open_device(ControllerType::M2T550);
+        mock.queue_response_with_crc(&[0xCF, 0x00, 0x00, 0x01]);
+        assert!(device.firmware_supports(Feature::CurrentSensing)?);
+        assert!(device.firmware_supports(Feature::CurrentLimiting)?);
+        assert_eq!(mock.writes(), [frame(&[0x87])]);
+        Ok(())
+    }
+
+    #[test]
+    fn response_with_bad_crc_is_rejected() {
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        mock.queue_response(&[0xCC, 0x00, 0x02, 0x01, 0x00]);
+        assert!(matches!(
+            device.firmware_version(),
+            Err(Error::Command(CommandsError::InvalidResponseCrc { .. }))
+        ));
+    }
+
+    #[test]
+    fn crc_mismatch_resends_command() -> Result {
+        let mock = MockI2c::new();
+        let mut device = DeviceBuilder::new().crc_mismatch_retry(1).build(
+            ControllerType::M2T256,
+            mock.clone(),
+            0x10,
+        )?;
+        mock.take_writes();
+        mock.queue_response(&[0xCC, 0x00, 0x02, 0x01, 0x00]);
+        mock.queue_response_with_crc(&[0xCC, 0x00, 0x02, 0x01]);
+        assert_eq!(device.refresh_firmware_version()?.product_id, 0xCC);
+        assert_eq!(mock.writes(), [frame(&[0x87]), frame(&[0x87])]);
+        Ok(())
+    }
+
+    #[test]
+    fn crc_mismatch_retries_exhausted() -> Result {
+        let mock = MockI2c::new();
+        let mut device = DeviceBuilder::new().crc_mismatch_retry(1).build(
+            ControllerType::M2T256,
+            mock.clone(),
+            0x10,
+        )?;
+        mock.take_writes();
+        mock.queue_response(&[0xCC, 0x00, 0x02, 0x01, 0x00]);
+        mock.queue_response(&[0xCC, 0x00, 0x02, 0x01, 0x00]);
+        assert!(matches!(
+            device.refresh_firmware_version(),
+            Err(Error::CrcRetriesExhausted { attempts: 2, .. })
+        ));
+        assert_eq!(mock.writes(), [frame(&[0x87]), frame(&[0x87])]);
+        Ok(())
+    }
+
+    #[test]
+    fn empty_read_is_retried() -> Result {
+        let mock = MockI2c::new();
+        let mut device = DeviceBuilder::new().retry_empty_reads(true).build(
+            ControllerType::M2T256,
+            mock.clone(),
+            0x10,
+        )?;
+        mock.take_writes();
+        mock.queue_response(&[]);
+        mock.queue_response_with_crc(&[0xCC, 0x00, 0x02, 0x01]);
+        assert_eq!(device.refresh_firmware_version()?.product_id, 0xCC);
+        assert_eq!(mock.writes(), [frame(&[0x87]), frame(&[0x87])]);
+        Ok(())
+    }
+
+    #[test]
+    fn empty_read_retry_gives_up() -> Result {
+        let mock = MockI2c::new();
+        let mut device = DeviceBuilder::new().retry_empty_reads(true).build(
+            ControllerType::M2T256,
+            mock.clone(),
+            0x10,
+        )?;
+        mock.queue_response(&[]);
+        mock.queue_response(&[]);
+        assert!(matches!(
+            device.refresh_firmware_version(),
+            Err(Error::Command(CommandsError::InvalidResponseLength {
+                expected: 5,
+                actual: 0,
+            }))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn empty_read_without_retry() {
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        mock.queue_response(&[]);
+        assert!(matches!(
+            device.refresh_firmware_version(),
+            Err(Error::Command(CommandsError::InvalidResponseCrc { .. }))
+        ));
+        assert_eq!(mock.writes(), [frame(&[0x87])]);
+    }
+
+    #[test]
+    fn commands_without_crc() -> Result {
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        device.set_command_crc(false)?;
+        device.set_response_crc(false)?;
+        mock.take_writes();
+        mock.queue_response(&[0xCC, 0x00, 0x02, 0x01]);
+        device.firmware_version()?;
+        assert_eq!(mock.writes(), [vec![0x87]]);
+        Ok(())
+    }
+
+    #[test]
+    fn max_speed_scale_halves_encoded_speed() -> Result {
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        device.set_speed(0, 0.5)?;
+        device.set_max_speed_scale(400);
+        device.set_speed(0, 0.5)?;
+        // 400 and then 200
+        assert_eq!(
+            mock.writes(),
+            [
+                frame(&[0xD1, 0x01, 0x10, 0x03]),
+                frame(&[0xD1, 0x01, 0x48, 0x01]),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn set_all_speeds_now_writes_frame() -> Result {
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        device.set_all_speeds_now(&[0.5, -0.5])?;
+        assert_eq!(mock.writes(), [frame(&[0xE2, 0x10, 0x03, 0x70, 0x7C])]);
+        Ok(())
+    }
+
+    #[test]
+    fn set_speed_now_writes_frame() -> Result {
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        device.set_speed_now(1, 0.5)?;
+        // Motor 1 is sent as motor 2, as the protocol's motor numbers are one-indexed
+        assert_eq!(mock.writes(), [frame(&[0xD2, 0x02, 0x10, 0x03])]);
+        Ok(())
+    }
+
+    #[test]
+    fn multi_device_error_check_decodes_response() -> Result {
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        device.set_response_crc(false)?;
+        mock.take_writes();
+        mock.queue_response(&[0x3C]);
+        mock.queue_response(&[0x00]);
+        assert_eq!(
+            device.multi_device_error_check(0x10, 2)?,
+            MultiDeviceErrorCheckResponse::Ok
+        );
+        assert_eq!(
+            device.multi_device_error_check(0x10, 2)?,
+            MultiDeviceErrorCheckResponse::ErrorActive
+        );
+        assert_eq!(mock.writes(), vec![frame(&[0xF9, 0x10, 0x02]); 2]);
+        Ok(())
+    }
+
+    #[test]
+    fn set_speed_rpm_scales_by_max_rpm() -> Result {
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        device.set_max_rpm(0, 200.)?;
+        device.set_speed_rpm(0, 100.)?;
+        // Half speed, which is 400
+        assert_eq!(mock.writes(), [frame(&[0xD1, 0x01, 0x10, 0x03])]);
+        Ok(())
+    }
+
+    #[test]
+    fn emergency_stop_coasts_now() -> Result {
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        device.emergency_stop()?;
+        assert_eq!(mock.writes(), [frame(&[0xA5])]);
+        Ok(())
+    }
+
+    #[test]
+    fn set_speed_clamped_saturates() -> Result {
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        device.set_speed_clamped(0, 1.5)?;
+        device.set_speed_clamped(0, -2.0)?;
+        // 800 and -800
+        assert_eq!(
+            mock.writes(),
+            [
+                frame(&[0xD1, 0x01, 0x20, 0x06]),
+                frame(&[0xD1, 0x01, 0x60, 0x79]),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn non_finite_speeds_rejected() -> Result {
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        device.set_max_rpm(0, 200.)?;
+        for speed in [f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+            assert!(matches!(
+                device.set_speed(0, speed),
+                Err(Error::InvalidSpeed(_))
+            ));
+            assert!(matches!(
+                device.set_speed_clamped(0, speed),
+                Err(Error::InvalidSpeed(_))
+            ));
+            assert!(matches!(
+                device.set_speed_rpm(0, speed),
+                Err(Error::InvalidSpeed(_))
+            ));
+            assert!(matches!(
+                device.set_all_speeds(&[0., speed]),
+                Err(Error::InvalidSpeed(_))
+            ));
+        }
+        assert!(mock.writes().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn set_all_same_speed_on_three_motors() -> Result {
+        let (mut device, mock) = open_device(ControllerType::M3S256);
+        device.set_all_same_speed(0.5)?;
+        device.set_speeds([0.5; 3])?;
+        assert_eq!(
+            mock.writes(),
+            vec![frame(&[0xE1, 0x10, 0x03, 0x10, 0x03, 0x10, 0x03]); 2]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn retries_failed_transfers() -> Result {
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        device.set_retries(2);
+        mock.fail_next(2);
+        device.coast_now()?;
+        assert_eq!(mock.writes(), [frame(&[0xA5])]);
+        Ok(())
+    }
+
+    #[test]
+    fn retries_failed_reads() -> Result {
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        device.set_retries(2);
+        mock.queue_response_with_crc(&[0xCE, 0x00, 0x02, 0x01]);
+        device.write_command(&GetFirmwareVersion)?;
+        // Only fail the read, after the command went through
+        mock.fail_next(2);
+        assert_eq!(device.read_command(&GetFirmwareVersion)?.product_id, 0xCE);
+        Ok(())
+    }
+
+    #[test]
+    fn gives_up_after_retries() {
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        device.set_retries(1);
+        mock.fail_next(2);
+        assert!(matches!(device.coast_now(), Err(Error::I2c(_))));
+        assert!(mock.writes().is_empty());
+    }
+
+    #[test]
+    fn set_starting_speed_writes_frames() -> Result {
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        device.set_starting_speed(1, 100, 200)?;
+        assert_eq!(
+            mock.writes(),
+            [
+                frame(&[0x9C, 0x02, 0x12, 0x64, 0x00]),
+                frame(&[0x9C, 0x02, 0x14, 0x48, 0x01]),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn debug_shows_controller_type() {
+        let (device, mock) = open_device(ControllerType::M2T256);
+        assert!(format!("{device:?}").contains("M2T256"));
+        assert!(mock.writes().is_empty());
+    }
+
+    #[test]
+    fn motor_indices_cover_every_motor() {
+        let (device, _mock) = open_device(ControllerType::M3S256);
+        assert_eq!(device.motor_count(), 3);
+        assert_eq!(device.motor_indices().collect::<Vec<_>>(), [0, 1, 2]);
+    }
+
+    #[test]
+    fn reinitialise_writes_frames() -> Result {
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        device.reinitialise()?;
+        assert_eq!(
+            mock.writes(),
+            [
+                frame(&[0x96]),
+                frame(&[0x8B, 0x07, 0x78]),
+                frame(&[0xA9, 0x00, 0x04]),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn drop_coasts_if_enabled() -> Result {
+        let mock = MockI2c::new();
+        let device = DeviceBuilder::new().coast_on_drop(true).build(
+            ControllerType::M2T256,
+            mock.clone(),
+            0x10,
+        )?;
+        mock.take_writes();
+        drop(device);
+        assert_eq!(mock.writes(), [frame(&[0xA5])]);
+
+        let (device, mock) = open_device(ControllerType::M2T256);
+        drop(device);
+        assert!(mock.writes().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn set_speed_quantized_returns_quantized_speed() -> Result {
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        // 0.3334 * 800 is 266.72, which is truncated to 266
+        assert_eq!(device.set_speed_quantized(0, 0.3334)?, 266. / 800.);
+        assert_eq!(mock.writes(), [frame(&[0xD1, 0x01, 0x0A, 0x02])]);
+        Ok(())
+    }
+
+    #[test]
+    fn recording_writes_each_frame() -> Result {
+        /// A writer whose contents can still be read after handing it over to the device.
+        #[derive(Clone, Default)]
+        struct SharedBuffer(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+        impl std::io::Write for SharedBuffer {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().write(buf)
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let (mut device, _mock) = open_device(ControllerType::M2T256);
+        let buffer = SharedBuffer::default();
+        device.start_recording(buffer.clone());
+        device.set_speed(0, 0.5)?;
+        device.set_speed(1, -0.5)?;
+        device.stop_recording().unwrap();
+        device.set_speed(0, 0.0)?;
+
+        let recorded = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<&str> = recorded.lines().collect();
+        assert_eq!(lines.len(), 2);
+        let crc = get_crc(&[0xD1, 0x01, 0x10, 0x03]);
+        assert!(lines[0].ends_with(&format!(" d1 01 10 03 {crc:02x}")));
+        let crc = get_crc(&[0xD1, 0x02, 0x70, 0x7C]);
+        assert!(lines[1].ends_with(&format!(" d1 02 70 7c {crc:02x}")));
+        Ok(())
+    }
+
+    #[test]
+    fn set_multi_speed_invalid_motor_writes_nothing() {
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        assert!(matches!(
+            device.set_multi_speed(&[(0, 0.5), (5, 0.5), (1, 0.5)]),
+            Err(Error::InvalidMotor {
+                provided: 5,
+                num_motors: 2,
+            })
+        ));
+        assert!(mock.writes().is_empty());
+    }
+
+    #[test]
+    fn set_multi_speed_now_commits_with_f3() -> Result {
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        device.set_multi_speed_now(&[(0, 0.5), (1, -0.5)])?;
+        assert_eq!(
+            mock.writes(),
+            [
+                frame(&[0xD4, 0x01, 0x10, 0x03]),
+                frame(&[0xD4, 0x02, 0x70, 0x7C]),
+                frame(&[0xF3]),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn error_checking_reports_protocol_error() {
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        device.set_error_checking(true);
+        mock.queue_response_with_crc(&[0x01, 0x00]);
+        assert!(matches!(
+            device.coast_now(),
+            Err(Error::ProtocolError(0x0001))
+        ));
+        assert_eq!(
+            mock.writes(),
+            [
+                frame(&[0xA5]),
+                frame(&[0x9A, 0x00, 0x01, 0x02]),
+                frame(&[0xA9, 0x03, 0x00]),
+            ]
+        );
+    }
+
+    #[test]
+    fn latched_and_current_status_flags() -> Result {
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        mock.queue_response_with_crc(&[0x01, 0xC2]);
+        assert_eq!(device.get_latched_status_flags()?, 0x0201);
+        mock.queue_response_with_crc(&[0x01, 0xC2]);
+        assert_eq!(device.get_current_status_flags()?, 0xC000);
+        assert_eq!(mock.writes(), vec![frame(&[0x9A, 0x00, 0x01, 0x02]); 2]);
+        Ok(())
+    }
+
+    #[test]
+    fn set_address_rewrites_protocol_options() -> Result {
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        device.set_address(0x11)?;
+        device.coast_now()?;
+        assert_eq!(
+            mock.addressed_writes(),
+            [(0x11, frame(&[0x8B, 0x07, 0x78])), (0x11, frame(&[0xA5])),]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn set_device_number_writes_eeprom() -> Result {
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        device.set_device_number(0x11)?;
+        assert_eq!(
+            mock.writes(),
+            [
+                frame(&[0x95, 0x01, 0x11, 0x00, 0x7E, 0x6E, 0x7F]),
+                frame(&[0x95, 0x02, 0x00, 0x00, 0x7D, 0x7F, 0x7F]),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn reset_and_wait_polls_until_device_answers() -> Result {
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        // Empty responses don't match the length of the read, so the first two polls fail
+        mock.queue_response(&[]);
+        mock.queue_response(&[]);
+        mock.queue_response_with_crc(&[0xCE, 0x00, 0x02, 0x01]);
+        device.reset_and_wait(Duration::from_secs(1))?;
+        let options = frame(&[0x8B, 0x07, 0x78]);
+        let version = frame(&[0x87]);
+        assert_eq!(
+            mock.writes(),
+            [
+                frame(&[0x99]),
+                options.clone(),
+                version.clone(),
+                options.clone(),
+                version.clone(),
+                options,
+                version,
+                frame(&[0xA9, 0x00, 0x04]),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn reset_and_wait_times_out() {
+        let (mut device, _mock) = open_device(ControllerType::M2T256);
+        assert!(matches!(
+            device.reset_and_wait(Duration::from_millis(5)),
+            Err(Error::ResetTimeout)
+        ));
+    }
+
+    #[test]
+    fn set_speeds_map_buffers_then_commits() -> Result {
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        let speeds = std::collections::HashMap::from([(1, -0.5), (0, 0.5)]);
+        device.set_speeds_map(&speeds)?;
+        assert_eq!(
+            mock.writes(),
+            [
+                frame(&[0xD4, 0x01, 0x10, 0x03]),
+                frame(&[0xD4, 0x02, 0x70, 0x7C]),
+                frame(&[0xF0]),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn reset_skips_error_check() -> Result {
+        let mock = MockI2c::new();
+        let mut device = DeviceBuilder::new().reset_delay(Duration::ZERO).build(
+            ControllerType::M2T256,
+            mock.clone(),
+            0x10,
+        )?;
+        device.set_error_checking(true);
+        mock.take_writes();
+        mock.queue_response_with_crc(&[0x00, 0x00]);
+        mock.queue_response_with_crc(&[0x00, 0x00]);
+        device.reset()?;
+        assert_eq!(
+            mock.writes(),
+            [
+                frame(&[0x99]),
+                frame(&[0x8B, 0x07, 0x78]),
+                frame(&[0x9A, 0x00, 0x01, 0x02]),
+                frame(&[0xA9, 0x00, 0x04]),
+                frame(&[0x9A, 0x00, 0x01, 0x02]),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn reset_and_wait_skips_error_check() -> Result {
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        device.set_error_checking(true);
+        mock.queue_response_with_crc(&[0x00, 0x00]);
+        mock.queue_response_with_crc(&[0xCC, 0x00, 0x02, 0x01]);
+        mock.queue_response_with_crc(&[0x00, 0x00]);
+        device.reset_and_wait(Duration::from_millis(100))?;
+        assert_eq!(mock.writes()[0], frame(&[0x99]));
+        assert_eq!(mock.writes()[1], frame(&[0x8B, 0x07, 0x78]));
+        Ok(())
+    }
+
+    #[test]
+    fn with_address_keeps_caches_separate() -> Result {
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        mock.queue_response_with_crc(&[0xCC, 0x00, 0x02, 0x01]);
+        device.firmware_version()?;
+        mock.queue_response_with_crc(&[0xCE, 0x00, 0x03, 0x01]);
+        let other = device.with_address(0x11, |device| device.firmware_version())?;
+        assert_eq!(other.product_id, 0xCE);
+        assert_eq!(device.firmware_version()?.product_id, 0xCC);
+        assert_eq!(
+            mock.addressed_writes(),
+            [(0x10, frame(&[0x87])), (0x11, frame(&[0x87]))]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn with_address_checks_reset_latch_of_other_device() -> Result {
+        let mock = MockI2c::new();
+        let mut device = DeviceBuilder::new().guard_reset_latch(true).build(
+            ControllerType::M2T256,
+            mock.clone(),
+            0x10,
+        )?;
+        mock.queue_response_with_crc(&[0x00, 0x00]);
+        device.set_speed(0, 0.0)?;
+        mock.queue_response_with_crc(&[0x00, 0x00]);
+        device.with_address(0x11, |device| device.set_speed(0, 0.0))?;
+        mock.take_writes();
+        // The original device was already checked, so this doesn't read the flags again
+        device.set_speed(0, 0.0)?;
+        assert_eq!(mock.writes(), [frame(&[0xD1, 0x01, 0x00, 0x00])]);
+        Ok(())
+    }
+
+    #[test]
+    fn with_address_writes_to_other_address() -> Result {
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        device.with_address(0x11, |device| device.coast_now())?;
+        device.coast_now()?;
+        assert_eq!(
+            mock.addressed_writes(),
+            [(0x11, frame(&[0xA5])), (0x10, frame(&[0xA5]))]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn with_address_restores_address_on_error() -> Result {
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        let result = device.with_address(0x11, |device| -> Result {
+            device.coast_now()?;
+            Err(Error::ResetTimeout)
+        });
+        assert!(matches!(result, Err(Error::ResetTimeout)));
+        device.coast_now()?;
+        assert_eq!(
+            mock.addressed_writes(),
+            [(0x11, frame(&[0xA5])), (0x10, frame(&[0xA5]))]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn verify_controller_type_matches() -> Result {
+        let (mut device, mock) = open_device(ControllerType::M2U256);
+        mock.queue_response_with_crc(&[0xCE, 0x00, 0x02, 0x01]);
+        device.verify_controller_type()
+    }
+
+    #[test]
+    fn verify_controller_type_mismatch() {
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        mock.queue_response_with_crc(&[0xCD, 0x00, 0x02, 0x01]);
+        assert!(matches!(
+            device.verify_controller_type(),
+            Err(Error::ControllerMismatch {
+                expected: ControllerType::M2T256,
+                actual: ControllerType::M3S550,
+            })
+        ));
+    }
+
+    #[test]
+    fn verify_controller_type_unknown_product() -> Result {
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        mock.queue_response_with_crc(&[0x34, 0x12, 0x02, 0x01]);
+        device.verify_controller_type()
+    }
+
+    #[test]
+    fn detected_channel_count() -> Result {
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        mock.queue_response_with_crc(&[0xCD, 0x00, 0x02, 0x01]);
+        assert_eq!(device.detected_channel_count()?, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn detected_channel_count_unknown_product() -> Result {
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        mock.queue_response_with_crc(&[0x34, 0x12, 0x02, 0x01]);
+        assert_eq!(device.detected_channel_count()?, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn verify_channel_count_same_count() -> Result {
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        mock.queue_response_with_crc(&[0xD2, 0x00, 0x02, 0x01]);
+        device.verify_channel_count()
+    }
+
+    #[test]
+    fn verify_channel_count_mismatch() {
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        mock.queue_response_with_crc(&[0xD0, 0x00, 0x02, 0x01]);
+        assert!(matches!(
+            device.verify_channel_count(),
+            Err(Error::ChannelCountMismatch {
+                expected: 2,
+                actual: 1,
+            })
+        ));
+    }
+
+    #[test]
+    fn set_address_rejects_address_above_7_bits() {
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        assert!(matches!(
+            device.set_address(0x80),
+            Err(Error::InvalidAddress(0x80))
+        ));
+        assert!(mock.writes().is_empty());
+    }
+
+    #[test]
+    fn with_address_rejects_address_above_7_bits() {
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        assert!(matches!(
+            device.with_address(0x80, |device| device.coast_now()),
+            Err(Error::InvalidAddress(0x80))
+        ));
+        assert!(mock.writes().is_empty());
+    }
+
+    #[test]
+    fn write_commands_uses_single_transaction() -> Result {
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        let cmds: [Box<dyn Command<Response = ()>>; 2] =
+            [Box::new(CoastNow), Box::new(ResetCommandTimeout)];
+        device.write_commands(&cmds)?;
+        let mut expected = frame(&[0xA5]);
+        expected.extend(frame(&[0xF5]));
+        assert_eq!(mock.writes(), [expected]);
+        Ok(())
+    }
+
+    #[test]
+    fn write_commands_checks_total_length() -> Result {
+        let mock = MockI2c::new();
+        let mut device = DeviceBuilder::new()
+            .max_write_len(3)
+            .write_options_on_open(false)
+            .build(ControllerType::M2T256, mock.clone(), 0x10)?;
+        let cmds: [Box<dyn Command<Response = ()>>; 2] =
+            [Box::new(CoastNow), Box::new(ResetCommandTimeout)];
+        assert!(matches!(
+            device.write_commands(&cmds),
+            Err(Error::CommandTooLong { len: 4, max: 3 })
+        ));
+        assert!(mock.writes().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn read_current_scales_by_controller() -> Result {
+        // 1024 counts at 3300 mV is 3300 mV of current sense output
+        let (mut device, mock) = open_device(ControllerType::M2T550);
+        mock.queue_response_with_crc(&[0xCF, 0x00, 0x00, 0x01]);
+        mock.queue_response_with_crc(&[0x00, 0x04]);
+        assert_eq!(device.read_current(1)?, 16500);
+        assert_eq!(
+            mock.take_writes(),
+            [frame(&[0x87]), frame(&[0x9A, 0x02, 0x1E, 0x02])]
+        );
+
+        // The firmware version is cached after the first read
+        mock.queue_response_with_crc(&[0x00, 0x04]);
+        device.read_current(0)?;
+        assert_eq!(mock.writes(), [frame(&[0x9A, 0x01, 0x1E, 0x02])]);
+
+        // 10 counts at 5000 mV is about 48.8 mV of current sense output
+        let (mut device, mock) = open_device(ControllerType::M2S24v14);
+        mock.queue_response_with_crc(&[0xD2, 0x00, 0x00, 0x01]);
+        mock.queue_response_with_crc(&[0x0A, 0x00]);
+        assert_eq!(device.read_current(0)?, 2441);
+        Ok(())
+    }
+
+    #[test]
+    fn read_current_uses_configured_reference() -> Result {
+        let mock = MockI2c::new();
+        let mut device = DeviceBuilder::new().reference_mv(5000).build(
+            ControllerType::M2T550,
+            mock.clone(),
+            0x10,
+        )?;
+        mock.queue_response_with_crc(&[0xCF, 0x00, 0x00, 0x01]);
+        mock.queue_response_with_crc(&[0x00, 0x04]);
+        assert_eq!(device.read_current(0)?, 25000);
+        Ok(())
+    }
+
+    #[test]
+    fn read_current_unsupported() {
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        assert!(matches!(
+            device.read_current(0),
+            Err(Error::UnsupportedFeature(Feature::CurrentSensing))
+        ));
+        assert!(mock.writes().is_empty());
+    }
+
+    #[test]
+    fn read_input_voltage_scales_by_controller() -> Result {
+        // 512 counts is half the logic voltage, before the VIN divider
+        let cases = [
+            (ControllerType::M2T550, 3.3 * 459. / 47.),
+            (ControllerType::M3S550, 5. * 459. / 47.),
+            (ControllerType::M2T256, 3.3 * 1047. / 47.),
+            (ControllerType::M3S256, 5. * 1047. / 47.),
+            (ControllerType::M2H24v14, 3.3 * 1047. / 47.),
+            (ControllerType::M2S24v14, 5. * 1047. / 47.),
+        ];
+        for (controller_type, expected) in cases {
+            let (mut device, mock) = open_device(controller_type);
+            mock.queue_response_with_crc(&[0x00, 0x02]);
+            let voltage = device.read_input_voltage()?;
+            assert!(
+                (voltage - expected / 2.).abs() < 0.001,
+                "{controller_type:?}: {voltage}"
+            );
+            assert_eq!(mock.writes(), [frame(&[0x9A, 0x00, 0x03, 0x02])]);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn set_current_limit_writes_variable() -> Result {
+        let (mut device, mock) = open_device(ControllerType::M2S24v14);
+        mock.queue_response_with_crc(&[0xD2, 0x00, 0x00, 0x01]);
+        device.set_current_limit(1, 0x1234)?;
+        assert_eq!(
+            mock.writes(),
+            [frame(&[0x87]), frame(&[0x9C, 0x02, 0x18, 0x34, 0x24])]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn set_current_limit_on_550_controller() -> Result {
+        let (mut device, mock) = open_device(ControllerType::M2T550);
+        mock.queue_response_with_crc(&[0xCF, 0x00, 0x00, 0x01]);
+        device.set_current_limit(0, 100)?;
+        assert_eq!(
+            mock.writes(),
+            [frame(&[0x87]), frame(&[0x9C, 0x01, 0x18, 0x64, 0x00])]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn set_current_limit_unsupported() {
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        assert!(matches!(
+            device.set_current_limit(0, 100),
+            Err(Error::UnsupportedFeature(Feature::CurrentLimiting))
+        ));
+        assert!(mock.writes().is_empty());
+    }
+
+    #[test]
+    fn set_speed_writes_frame() -> Result {
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        device.set_speed(1, 1.0)?;
+        assert_eq!(mock.writes(), [frame(&[0xD1, 0x02, 0x20, 0x06])]);
+        Ok(())
+    }
+
+    #[test]
+    fn get_variable_reads_response() -> Result {
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        mock.queue_response_with_crc(&[0x34, 0x12]);
+        assert_eq!(device.get_max_acceleration(0)?, 0x1234);
+        assert_eq!(mock.writes(), [frame(&[0x9A, 0x01, 0x0A, 0x02])]);
+        Ok(())
+    }
+
+    #[test]
+    fn bus_errors_are_mapped() {
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        mock.fail_next(1);
+        match device.coast_now() {
+            Err(Error::I2c(e)) => {
+                assert_eq!(
+                    e.kind,
+                    ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address)
+                )
+            }
+            result => panic!("unexpected result: {result:?}"),
+        }
+    }
+
+    #[test]
+    fn duplicate_motor_index_keeps_last_speed() -> Result {
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        device.set_multi_speed(&[(0, 0.5), (0, -0.5)])?;
+        assert_eq!(
+            mock.writes(),
+            [frame(&[0xD4, 0x01, 0x70, 0x7C]), frame(&[0xF0])]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn duplicate_motor_index_rejected() -> Result {
+        let mock = MockI2c::new();
+        let mut device = DeviceBuilder::new().reject_duplicate_motors(true).build(
+            ControllerType::M2T256,
+            mock.clone(),
+            0x10,
+        )?;
+        mock.take_writes();
+        assert!(matches!(
+            device.set_multi_speed(&[(0, 0.5), (1, 0.0), (0, -0.5)]),
+            Err(Error::DuplicateMotorIndex(0))
+        ));
+        assert!(mock.writes().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn dump_all_variables_splits_reads() -> Result {
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        let variables: Vec<u8> = (0..35).collect();
+        mock.queue_response_with_crc(&variables[..32]);
+        mock.queue_response_with_crc(&variables[32..]);
+        assert_eq!(device.dump_all_variables(1)?, variables);
+        assert_eq!(
+            mock.writes(),
+            [
+                frame(&[0x9A, 0x02, 0x00, 0x20]),
+                frame(&[0x9A, 0x02, 0x20, 0x03]),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn dump_device_variables_single_read() -> Result {
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        let variables: Vec<u8> = (0..12).collect();
+        mock.queue_response_with_crc(&variables);
+        assert_eq!(device.dump_device_variables()?, variables);
+        assert_eq!(mock.writes(), [frame(&[0x9A, 0x00, 0x00, 0x0C])]);
+        Ok(())
+    }
+
+    #[test]
+    fn braking_cmd_rejects_invalid_motor() {
+        for motor_idx in [2, 255] {
+            assert!(matches!(
+                get_braking_cmd(ControllerType::M2T256, motor_idx, 0.5, BrakingMode::Normal),
+                Err(Error::InvalidMotor {
+                    provided,
+                    num_motors: 2,
+                }) if provided == motor_idx
+            ));
+        }
+    }
+
+    #[test]
+    fn two_byte_variable_round_trip() -> Result {
+        let (mut device, mock) = open_device(ControllerType::M2T550);
+        device.set_variable_typed(1, Variable::CurrentSenseMinimumDivisor, 0x1234)?;
+        mock.queue_response_with_crc(&[0x34, 0x12]);
+        assert_eq!(
+            device.get_variable_typed(1, Variable::CurrentSenseMinimumDivisor)?,
+            0x1234
+        );
+        assert_eq!(
+            mock.writes(),
+            [
+                frame(&[0x9C, 0x01, 0x21, 0x34, 0x24]),
+                frame(&[0x9A, 0x01, 0x21, 0x02]),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn inverted_motor_negates_speed() -> Result {
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        device.set_motor_inverted(0, true)?;
+        device.set_speed(0, 1.0)?;
+        // -800 is 0xFCE0 in 2's complement, which splits into 0x60 and 0x79
+        assert_eq!(mock.writes(), [frame(&[0xD1, 0x01, 0x60, 0x79])]);
+        Ok(())
+    }
+
+    #[test]
+    fn inverted_motor_reports_original_speed() -> Result {
+        let (mut device, _mock) = open_device(ControllerType::M2T256);
+        device.set_motor_inverted(0, true)?;
+        assert!(matches!(
+            device.set_speed(0, 1.5),
+            Err(Error::InvalidSpeed(speed)) if speed == 1.5
+        ));
+        assert!(matches!(
+            device.set_all_speeds(&[1.5, 0.0]),
+            Err(Error::InvalidSpeed(speed)) if speed == 1.5
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn status_predicates_decode_flags() -> Result {
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        mock.queue_response_with_crc(&[0x04, 0x00]);
+        assert!(device.command_timeout_occurred()?);
+        mock.queue_response_with_crc(&[0x04, 0x00]);
+        assert!(!device.motor_faulting(1)?);
+        mock.queue_response_with_crc(&[0x00, 0x08]);
+        assert!(!device.command_timeout_occurred()?);
+        mock.queue_response_with_crc(&[0x00, 0x08]);
+        assert!(device.motor_faulting(1)?);
+        assert_eq!(mock.writes(), vec![frame(&[0x9A, 0x00, 0x01, 0x02]); 4]);
+        Ok(())
+    }
+
+    #[test]
+    fn set_braking_writes_frame() -> Result {
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        device.set_braking(0, 0.5)?;
+        device.set_braking_now(0, 0.5)?;
+        // Half braking is 400, which splits into 0x10 and 0x03
+        assert_eq!(
+            mock.writes(),
+            [
+                frame(&[0xB1, 0x01, 0x10, 0x03]),
+                frame(&[0xB2, 0x01, 0x10, 0x03]),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn set_max_acceleration_and_deceleration_write_frames() -> Result {
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        device.set_max_acceleration(0, 100)?;
+        device.set_max_deceleration(1, 0xFFFF)?;
+        assert_eq!(
+            mock.writes(),
+            [
+                frame(&[0x9C, 0x01, 0x0A, 0x64, 0x00]),
+                frame(&[0x9C, 0x01, 0x0C, 0x64, 0x00]),
+                // Clamped to 0x3FFF
+                frame(&[0x9C, 0x02, 0x0E, 0x7F, 0x7F]),
+                frame(&[0x9C, 0x02, 0x10, 0x7F, 0x7F]),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn read_eeprom_round_trip() -> Result {
+        let (mut device, mock) = open_device(ControllerType::M2T256);
+        mock.queue_response_with_crc(&[0x10, 0x00, 0x00, 0x00]);
+        assert_eq!(device.read_eeprom(1, 4)?, [0x10, 0x00, 0x00, 0x00]);
+        mock.queue_response_with_crc(&[0x11, 0x00]);
+        assert_eq!(device.read_device_number()?, 0x11);
+        assert_eq!(
+            mock.writes(),
+            [frame(&[0x93, 0x01, 0x04]), frame(&[0x93, 0x01, 0x02])]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn telemetry_reads_snapshot() -> Result {
+        let (mut device, mock) = open_device(ControllerType::M2T550);
+        // Status flags, then 512 counts of VIN
+        mock.queue_response_with_crc(&[0x04, 0x02, 0x00, 0x02]);
+        let mut motor = vec![0; 26];
+        motor[..2].copy_from_slice(&400i16.to_le_bytes());
+        motor[24..].copy_from_slice(&100u16.to_le_bytes());
+        mock.queue_response_with_crc(&motor);
+        motor[..2].copy_from_slice(&(-200i16).to_le_bytes());
+        mock.queue_response_with_crc(&motor);
+
+        let telemetry = device.telemetry()?;
+        assert_eq!(telemetry.status_flags, 0x0204);
+        assert!((telemetry.input_voltage - 3.3 * 459. / 47. / 2.).abs() < 0.001);
+        assert_eq!(
+            telemetry.motors,
+            [
+                MotorTelemetry {
+                    speed: 0.5,
+                    current: Some(100),
+                },
+                MotorTelemetry {
+                    speed: -0.25,
+                    current: Some(100),
+                },
+            ]
+        );
+        assert_eq!(
+            mock.writes(),
+            [
+                frame(&[0x9A, 0x00, 0x01, 0x04]),
+                frame(&[0x9A, 0x01, 0x06, 0x1A]),
+                frame(&[0x9A, 0x02, 0x06, 0x1A]),
+            ]
+        );
+        Ok(())
     }
 }
@@ -0,0 +1,134 @@
+use embedded_hal::i2c::{
+    self, ErrorKind, ErrorType, I2c, NoAcknowledgeSource, Operation, SevenBitAddress,
+};
+use i2cdev::core::{I2CMessage, I2CTransfer};
+use i2cdev::linux::{LinuxI2CDevice, LinuxI2CError, LinuxI2CMessage};
+use std::fmt;
+use std::path::Path;
+
+/// An I2C bus on Linux, accessed through its device file (e.g. `/dev/i2c-0`). This is what
+/// [`Device::new_linux`](crate::Device::new_linux) uses under the hood, and it implements the
+/// [`embedded_hal`] I2C trait so it can be used with [`Device::new`](crate::Device::new) too.
+/// Each transaction is sent as a single `I2C_RDWR` transfer, so its operations are separated by
+/// repeated starts rather than stops.
+pub struct LinuxI2c {
+    device: LinuxI2CDevice,
+    address: SevenBitAddress,
+}
+
+impl LinuxI2c {
+    /// Opens the I2C bus at the given path. The address is the one the bus starts out pointed at,
+    /// and it only matters as a starting point, as every transaction re-targets the bus if needed.
+    pub fn new<P: AsRef<Path>>(
+        path: P,
+        address: SevenBitAddress,
+    ) -> Result<LinuxI2c, LinuxI2cError> {
+        Ok(LinuxI2c {
+            device: LinuxI2CDevice::new(path, address.into()).map_err(LinuxI2cError)?,
+            address,
+        })
+    }
+}
+
+impl ErrorType for LinuxI2c {
+    type Error = LinuxI2cError;
+}
+
+impl I2c for LinuxI2c {
+    fn transaction(
+        &mut self,
+        address: SevenBitAddress,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), LinuxI2cError> {
+        if address != self.address {
+            self.device
+                .set_slave_address(address.into())
+                .map_err(LinuxI2cError)?;
+            self.address = address;
+        }
+        if operations.is_empty() {
+            return Ok(());
+        }
+        let mut messages: Vec<_> = operations
+            .iter_mut()
+            .map(|operation| match operation {
+                Operation::Read(data) => LinuxI2CMessage::read(data),
+                Operation::Write(data) => LinuxI2CMessage::write(data),
+            })
+            .collect();
+        self.device.transfer(&mut messages).map_err(LinuxI2cError)?;
+        Ok(())
+    }
+}
+
+/// An error from the Linux I2C bus, wrapping the one returned by the `i2cdev` crate.
+#[derive(Debug)]
+pub struct LinuxI2cError(pub LinuxI2CError);
+
+impl fmt::Display for LinuxI2cError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl std::error::Error for LinuxI2cError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.0)
+    }
+}
+
+impl i2c::Error for LinuxI2cError {
+    /// Maps the errno of the failed call to the closest kind of error, following the kernel's
+    /// [I2C fault codes](https://www.kernel.org/doc/html/latest/i2c/fault-codes.html).
+    fn kind(&self) -> ErrorKind {
+        let errno = match &self.0 {
+            LinuxI2CError::Errno(errno) => Some(*errno),
+            LinuxI2CError::Io(e) => e.raw_os_error(),
+        };
+        match errno {
+            Some(libc::ENXIO | libc::ENODEV) => {
+                ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address)
+            }
+            Some(libc::EREMOTEIO) => ErrorKind::NoAcknowledge(NoAcknowledgeSource::Unknown),
+            Some(libc::EAGAIN) => ErrorKind::ArbitrationLoss,
+            Some(libc::EOVERFLOW) => ErrorKind::Overrun,
+            Some(libc::EIO | libc::EBUSY | libc::ETIMEDOUT | libc::EPROTO | libc::EBADMSG) => {
+                ErrorKind::Bus
+            }
+            _ => ErrorKind::Other,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_hal::i2c::Error;
+    use std::io;
+
+    #[test]
+    fn error_kinds() {
+        let kind = |errno| LinuxI2cError(LinuxI2CError::Errno(errno)).kind();
+        assert_eq!(
+            kind(libc::ENXIO),
+            ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address)
+        );
+        assert_eq!(
+            kind(libc::EREMOTEIO),
+            ErrorKind::NoAcknowledge(NoAcknowledgeSource::Unknown)
+        );
+        assert_eq!(kind(libc::EAGAIN), ErrorKind::ArbitrationLoss);
+        assert_eq!(kind(libc::EBUSY), ErrorKind::Bus);
+        assert_eq!(kind(libc::EINVAL), ErrorKind::Other);
+
+        let error = LinuxI2cError(LinuxI2CError::Io(io::Error::from_raw_os_error(
+            libc::EREMOTEIO,
+        )));
+        assert_eq!(
+            error.kind(),
+            ErrorKind::NoAcknowledge(NoAcknowledgeSource::Unknown)
+        );
+        let error = LinuxI2cError(LinuxI2CError::Io(io::ErrorKind::NotFound.into()));
+        assert_eq!(error.kind(), ErrorKind::Other);
+    }
+}
@@ -0,0 +1,195 @@
+use crate::commands::get_crc;
+use embedded_hal::i2c::{
+    ErrorKind, ErrorType, I2c, NoAcknowledgeSource, Operation, SevenBitAddress,
+};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+/// A fake I2C bus that records everything written to it and answers reads with responses you
+/// queue up beforehand, so you can test code using a [`Device`](crate::Device) without any
+/// hardware. Clones share the same state, so keep a clone around to inspect the bus after handing
+/// one over to the device. This is only available with the `mock` feature.
+///
+/// ```
+/// use pololu_motoron::{ControllerType, Device, MockI2c};
+///
+/// # fn main() -> anyhow::Result<()> {
+/// let mock = MockI2c::new();
+/// let mut device = Device::new(ControllerType::M2T256, mock.clone(), 0x10)?;
+///
+/// mock.queue_response_with_crc(&[0xCC, 0x00, 0x02, 0x01]);
+/// let version = device.firmware_version()?;
+/// assert_eq!(version.product_id, 0xCC);
+/// assert_eq!((version.major_fw_version, version.minor_fw_version), (1, 2));
+///
+/// mock.take_writes();
+/// device.set_all_speeds(&[0.5, -0.5])?;
+/// assert_eq!(mock.take_writes()[0][..5], [0xE1, 0x10, 0x03, 0x70, 0x7C]);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MockI2c {
+    state: Arc<Mutex<MockState>>,
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    writes: Vec<(SevenBitAddress, Vec<u8>)>,
+    responses: VecDeque<Vec<u8>>,
+    failures: usize,
+    present: Option<Vec<SevenBitAddress>>,
+}
+
+impl MockI2c {
+    /// Creates a bus with nothing written to it and no responses queued.
+    pub fn new() -> MockI2c {
+        MockI2c::default()
+    }
+
+    /// Queues up the raw bytes the next unanswered read will return. The read fails if its length
+    /// doesn't match the response, so remember to include the CRC byte if response CRCs are
+    /// enabled, or use [`MockI2c::queue_response_with_crc`]. An empty response acts like an
+    /// adapter that returns no data: the read succeeds but leaves the buffer untouched.
+    pub fn queue_response(&self, response: &[u8]) {
+        self.lock().responses.push_back(response.to_vec());
+    }
+
+    /// Same as [`MockI2c::queue_response`], but appends the CRC byte to the response for you.
+    pub fn queue_response_with_crc(&self, response: &[u8]) {
+        let mut response = response.to_vec();
+        response.push(get_crc(&response));
+        self.lock().responses.push_back(response);
+    }
+
+    /// Returns every write made so far, in order, including the CRC byte if command CRCs are
+    /// enabled.
+    pub fn writes(&self) -> Vec<Vec<u8>> {
+        self.lock()
+            .writes
+            .iter()
+            .map(|(_, data)| data.clone())
+            .collect()
+    }
+
+    /// Same as [`MockI2c::writes`], but also forgets them, so the next call only returns the
+    /// writes made after this one.
+    pub fn take_writes(&self) -> Vec<Vec<u8>> {
+        std::mem::take(&mut self.lock().writes)
+            .into_iter()
+            .map(|(_, data)| data)
+            .collect()
+    }
+
+    /// Same as [`MockI2c::writes`], but also returns the address each write was sent to.
+    pub fn addressed_writes(&self) -> Vec<(SevenBitAddress, Vec<u8>)> {
+        self.lock().writes.clone()
+    }
+
+    /// Makes the next `count` transactions fail as if nothing acknowledged them, without writing
+    /// anything or consuming any queued responses. This is useful to test how flaky buses are
+    /// handled.
+    pub fn fail_next(&self, count: usize) {
+        self.lock().failures = count;
+    }
+
+    /// Makes every transaction to an address other than the given ones fail as if nothing was
+    /// there, like a bus with only those devices on it. By default, every address answers.
+    pub fn set_present_addresses(&self, addresses: &[SevenBitAddress]) {
+        self.lock().present = Some(addresses.to_vec());
+    }
+
+    fn lock(&self) -> MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl ErrorType for MockI2c {
+    type Error = ErrorKind;
+}
+
+impl I2c for MockI2c {
+    fn transaction(
+        &mut self,
+        address: SevenBitAddress,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), ErrorKind> {
+        let mut state = self.lock();
+        if state.failures > 0 {
+            state.failures -= 1;
+            return Err(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address));
+        }
+        if let Some(present) = &state.present {
+            if !present.contains(&address) {
+                return Err(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address));
+            }
+        }
+        for operation in operations {
+            match operation {
+                Operation::Write(data) => state.writes.push((address, data.to_vec())),
+                Operation::Read(data) => {
+                    let response = state.responses.pop_front().ok_or(ErrorKind::Other)?;
+                    if response.is_empty() {
+                        continue;
+                    }
+                    if response.len() != data.len() {
+                        return Err(ErrorKind::Other);
+                    }
+                    data.copy_from_slice(&response);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_writes_with_addresses() {
+        let mut mock = MockI2c::new();
+        mock.write(0x10, &[1, 2]).unwrap();
+        mock.write(0x11, &[3]).unwrap();
+        assert_eq!(
+            mock.addressed_writes(),
+            [(0x10, vec![1, 2]), (0x11, vec![3])]
+        );
+        assert_eq!(mock.take_writes(), [vec![1, 2], vec![3]]);
+        assert!(mock.writes().is_empty());
+    }
+
+    #[test]
+    fn reads_return_queued_responses() {
+        let mut mock = MockI2c::new();
+        mock.queue_response(&[1, 2]);
+        let mut data = [0; 2];
+        mock.read(0x10, &mut data).unwrap();
+        assert_eq!(data, [1, 2]);
+        assert!(mock.read(0x10, &mut data).is_err());
+
+        mock.queue_response(&[]);
+        mock.read(0x10, &mut data).unwrap();
+        assert_eq!(data, [1, 2]);
+    }
+
+    #[test]
+    fn fails_requested_transactions() {
+        let mut mock = MockI2c::new();
+        mock.fail_next(2);
+        assert!(mock.write(0x10, &[1]).is_err());
+        assert!(mock.write(0x10, &[2]).is_err());
+        mock.write(0x10, &[3]).unwrap();
+        assert_eq!(mock.writes(), [vec![3]]);
+    }
+
+    #[test]
+    fn only_present_addresses_answer() {
+        let mut mock = MockI2c::new();
+        mock.set_present_addresses(&[0x10]);
+        assert!(mock.write(0x11, &[1]).is_err());
+        mock.write(0x10, &[2]).unwrap();
+        assert_eq!(mock.addressed_writes(), [(0x10, vec![2])]);
+    }
+}
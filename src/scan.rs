@@ -0,0 +1,85 @@
+use crate::commands::{decode_response, encode_command, FirmwareVersion, GetFirmwareVersion};
+#[cfg(feature = "linux")]
+use crate::{I2cError, LinuxI2c, Result};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::RangeInclusive;
+use embedded_hal::i2c::{I2c, SevenBitAddress};
+#[cfg(feature = "linux")]
+use std::path::Path;
+
+/// Probes every address in `addresses` for a Motoron controller, by asking for its firmware
+/// version, and returns the addresses that replied with a valid one, along with that version.
+/// This is handy to find a controller when you don't know what address it was configured with.
+/// Addresses where nothing answers (or where the answer isn't a valid response) are skipped.
+///
+/// This assumes the controllers have CRC enabled for both commands and responses, which is how
+/// they start up. Keep in mind that probing an address sends a command to it, so avoid scanning
+/// addresses used by other kinds of devices.
+pub fn scan_bus<I: I2c>(
+    i2c: &mut I,
+    addresses: RangeInclusive<SevenBitAddress>,
+) -> Vec<(SevenBitAddress, FirmwareVersion)> {
+    let cmd = GetFirmwareVersion;
+    let data = encode_command(&cmd, true).expect("GetFirmwareVersion has no arguments to check");
+    let mut found = Vec::new();
+    for address in addresses {
+        if i2c.write(address, &data).is_err() {
+            continue;
+        }
+        let mut response = vec![0; 5];
+        if i2c.read(address, &mut response).is_err() {
+            continue;
+        }
+        match decode_response::<GetFirmwareVersion>(response, true) {
+            Ok(version) => found.push((address, version)),
+            Err(e) => log::debug!("Ignoring invalid response from address {address:#04x}: {e}"),
+        }
+    }
+    found
+}
+
+/// Same as [`scan_bus`], but opens the Linux I2C bus at the given path (e.g. `/dev/i2c-0`)
+/// itself.
+#[cfg(feature = "linux")]
+pub fn scan_linux_bus<P: AsRef<Path>>(
+    device: P,
+    addresses: RangeInclusive<SevenBitAddress>,
+) -> Result<Vec<(SevenBitAddress, FirmwareVersion)>> {
+    let mut i2c = LinuxI2c::new(device, *addresses.start()).map_err(I2cError::new)?;
+    Ok(scan_bus(&mut i2c, addresses))
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::mock::MockI2c;
+
+    #[test]
+    fn finds_only_answering_devices() {
+        let mut mock = MockI2c::new();
+        mock.set_present_addresses(&[0x10]);
+        mock.queue_response_with_crc(&[0xCE, 0x00, 0x02, 0x01]);
+        let found = scan_bus(&mut mock, 0x08..=0x20);
+        assert_eq!(
+            found,
+            [(
+                0x10,
+                FirmwareVersion {
+                    product_id: 0xCE,
+                    minor_fw_version: 2,
+                    major_fw_version: 1,
+                }
+            )]
+        );
+        assert_eq!(mock.addressed_writes(), [(0x10, vec![0x87, 0x3C])]);
+    }
+
+    #[test]
+    fn skips_invalid_responses() {
+        let mut mock = MockI2c::new();
+        mock.set_present_addresses(&[0x10]);
+        mock.queue_response(&[0xCE, 0x00, 0x02, 0x01, 0x00]);
+        assert!(scan_bus(&mut mock, 0x10..=0x10).is_empty());
+    }
+}
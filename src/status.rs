@@ -0,0 +1,314 @@
+use alloc::vec::Vec;
+use core::fmt;
+
+/// A single flag from the controller's status flags variable. The latched flags (the ones up to
+/// and including [`StatusFlag::Reset`]) stay set until they are cleared, while the rest reflect
+/// the current state of the controller. For more details, see the description of the status
+/// flags in the [variable reference](https://www.pololu.com/docs/0J84).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StatusFlag {
+    ProtocolError,
+    CrcError,
+    CommandTimeoutLatched,
+    MotorFaultLatched,
+    NoPowerLatched,
+    UartError,
+    Reset,
+    CommandTimeout,
+    MotorFaulting,
+    NoPower,
+    ErrorActive,
+    MotorOutputEnabled,
+    MotorDriving,
+}
+
+impl StatusFlag {
+    /// Every status flag, in the order of their bits in the status flags variable.
+    pub const ALL: [StatusFlag; 13] = [
+        StatusFlag::ProtocolError,
+        StatusFlag::CrcError,
+        StatusFlag::CommandTimeoutLatched,
+        StatusFlag::MotorFaultLatched,
+        StatusFlag::NoPowerLatched,
+        StatusFlag::UartError,
+        StatusFlag::Reset,
+        StatusFlag::CommandTimeout,
+        StatusFlag::MotorFaulting,
+        StatusFlag::NoPower,
+        StatusFlag::ErrorActive,
+        StatusFlag::MotorOutputEnabled,
+        StatusFlag::MotorDriving,
+    ];
+
+    /// The position of this flag's bit in the status flags variable.
+    pub fn bit(&self) -> u8 {
+        match self {
+            StatusFlag::ProtocolError => 0,
+            StatusFlag::CrcError => 1,
+            StatusFlag::CommandTimeoutLatched => 2,
+            StatusFlag::MotorFaultLatched => 3,
+            StatusFlag::NoPowerLatched => 4,
+            StatusFlag::UartError => 5,
+            StatusFlag::Reset => 9,
+            StatusFlag::CommandTimeout => 10,
+            StatusFlag::MotorFaulting => 11,
+            StatusFlag::NoPower => 12,
+            StatusFlag::ErrorActive => 13,
+            StatusFlag::MotorOutputEnabled => 14,
+            StatusFlag::MotorDriving => 15,
+        }
+    }
+
+    /// Returns whether this flag is set in the raw status flags.
+    pub fn is_set(&self, flags: u16) -> bool {
+        flags & (1 << self.bit()) != 0
+    }
+}
+
+impl fmt::Display for StatusFlag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            StatusFlag::ProtocolError => "protocol error",
+            StatusFlag::CrcError => "CRC error",
+            StatusFlag::CommandTimeoutLatched => "command timeout (latched)",
+            StatusFlag::MotorFaultLatched => "motor fault (latched)",
+            StatusFlag::NoPowerLatched => "no power (latched)",
+            StatusFlag::UartError => "UART error",
+            StatusFlag::Reset => "reset",
+            StatusFlag::CommandTimeout => "command timeout",
+            StatusFlag::MotorFaulting => "motor faulting",
+            StatusFlag::NoPower => "no power",
+            StatusFlag::ErrorActive => "error active",
+            StatusFlag::MotorOutputEnabled => "motor output enabled",
+            StatusFlag::MotorDriving => "motor driving",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A snapshot of the state of a controller, as returned by
+/// [`Device::telemetry`](crate::Device::telemetry).
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Telemetry {
+    /// The raw status flags. You can check individual flags with [`StatusFlag::is_set`].
+    pub status_flags: u16,
+    /// The input voltage (VIN), in volts, scaled the same way as in
+    /// [`Device::read_input_voltage`](crate::Device::read_input_voltage).
+    pub input_voltage: f32,
+    /// The state of each motor, in order of motor index.
+    pub motors: Vec<MotorTelemetry>,
+}
+
+/// The state of a single motor, as part of a [`Telemetry`] snapshot.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MotorTelemetry {
+    /// The speed the motor is currently being driven at, between -1.0 and 1.0. This can differ
+    /// from the last speed commanded while accelerating or decelerating.
+    pub speed: f32,
+    /// The processed current sense reading of the motor, or `None` if this controller can't sense
+    /// current.
+    pub current: Option<u16>,
+}
+
+/// The controller's status flags, decoded into named fields, as returned by
+/// [`Device::motor_status`](crate::Device::motor_status). See [`StatusFlag`] for what each of them
+/// means.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MotorStatus {
+    pub protocol_error: bool,
+    pub crc_error: bool,
+    pub command_timeout_latched: bool,
+    pub motor_fault_latched: bool,
+    pub no_power_latched: bool,
+    pub uart_error: bool,
+    pub reset: bool,
+    pub command_timeout: bool,
+    pub motor_faulting: bool,
+    pub no_power: bool,
+    pub error_active: bool,
+    pub motor_output_enabled: bool,
+    pub motor_driving: bool,
+}
+
+impl From<u16> for MotorStatus {
+    fn from(flags: u16) -> MotorStatus {
+        MotorStatus {
+            protocol_error: StatusFlag::ProtocolError.is_set(flags),
+            crc_error: StatusFlag::CrcError.is_set(flags),
+            command_timeout_latched: StatusFlag::CommandTimeoutLatched.is_set(flags),
+            motor_fault_latched: StatusFlag::MotorFaultLatched.is_set(flags),
+            no_power_latched: StatusFlag::NoPowerLatched.is_set(flags),
+            uart_error: StatusFlag::UartError.is_set(flags),
+            reset: StatusFlag::Reset.is_set(flags),
+            command_timeout: StatusFlag::CommandTimeout.is_set(flags),
+            motor_faulting: StatusFlag::MotorFaulting.is_set(flags),
+            no_power: StatusFlag::NoPower.is_set(flags),
+            error_active: StatusFlag::ErrorActive.is_set(flags),
+            motor_output_enabled: StatusFlag::MotorOutputEnabled.is_set(flags),
+            motor_driving: StatusFlag::MotorDriving.is_set(flags),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::{encode_command, ClearLatchedStatusFlags, SetLatchedStatusFlags};
+
+    #[test]
+    fn clear_reset_flag_bit() {
+        let cmd = ClearLatchedStatusFlags {
+            reset: true,
+            ..Default::default()
+        };
+        let data = encode_command(&cmd, false).unwrap();
+        assert_eq!(data, [0xA9, 0x00, 0x04]);
+        let flags = u16::from(data[1]) | (u16::from(data[2]) << 7);
+        assert_eq!(flags, 1 << StatusFlag::Reset.bit());
+    }
+
+    #[test]
+    fn motor_status_from_raw_flags() {
+        assert_eq!(
+            MotorStatus::from(0x0204),
+            MotorStatus {
+                command_timeout_latched: true,
+                reset: true,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn decode_raw_flags() {
+        let flags = 0xC201;
+        let set: Vec<StatusFlag> = StatusFlag::ALL
+            .into_iter()
+            .filter(|flag| flag.is_set(flags))
+            .collect();
+        assert_eq!(
+            set,
+            [
+                StatusFlag::ProtocolError,
+                StatusFlag::Reset,
+                StatusFlag::MotorOutputEnabled,
+                StatusFlag::MotorDriving,
+            ]
+        );
+    }
+
+    #[test]
+    fn flag_bits() {
+        let expected = [
+            (StatusFlag::ProtocolError, 0),
+            (StatusFlag::CrcError, 1),
+            (StatusFlag::CommandTimeoutLatched, 2),
+            (StatusFlag::MotorFaultLatched, 3),
+            (StatusFlag::NoPowerLatched, 4),
+            (StatusFlag::UartError, 5),
+            (StatusFlag::Reset, 9),
+            (StatusFlag::CommandTimeout, 10),
+            (StatusFlag::MotorFaulting, 11),
+            (StatusFlag::NoPower, 12),
+            (StatusFlag::ErrorActive, 13),
+            (StatusFlag::MotorOutputEnabled, 14),
+            (StatusFlag::MotorDriving, 15),
+        ];
+        assert_eq!(expected.map(|(flag, _)| flag), StatusFlag::ALL);
+        for (flag, bit) in expected {
+            assert_eq!(flag.bit(), bit, "{flag:?}");
+        }
+    }
+
+    #[test]
+    fn set_latched_flag_bits() {
+        let set = |flags: SetLatchedStatusFlags| {
+            let data = encode_command(&flags, false).unwrap();
+            u16::from(data[1]) | (u16::from(data[2]) << 7)
+        };
+        let cases = [
+            (
+                SetLatchedStatusFlags {
+                    protocol_error: true,
+                    ..Default::default()
+                },
+                StatusFlag::ProtocolError,
+            ),
+            (
+                SetLatchedStatusFlags {
+                    crc_error: true,
+                    ..Default::default()
+                },
+                StatusFlag::CrcError,
+            ),
+            (
+                SetLatchedStatusFlags {
+                    command_timeout_latched: true,
+                    ..Default::default()
+                },
+                StatusFlag::CommandTimeoutLatched,
+            ),
+            (
+                SetLatchedStatusFlags {
+                    motor_fault_latched: true,
+                    ..Default::default()
+                },
+                StatusFlag::MotorFaultLatched,
+            ),
+            (
+                SetLatchedStatusFlags {
+                    no_power_latched: true,
+                    ..Default::default()
+                },
+                StatusFlag::NoPowerLatched,
+            ),
+            (
+                SetLatchedStatusFlags {
+                    uart_error: true,
+                    ..Default::default()
+                },
+                StatusFlag::UartError,
+            ),
+            (
+                SetLatchedStatusFlags {
+                    reset: true,
+                    ..Default::default()
+                },
+                StatusFlag::Reset,
+            ),
+        ];
+        for (flags, flag) in cases {
+            assert_eq!(set(flags), 1 << flag.bit(), "{flag:?}");
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn json_round_trip() {
+        use alloc::vec;
+
+        let status = MotorStatus::from(0x0204);
+        let json = serde_json::to_string(&status).unwrap();
+        assert_eq!(serde_json::from_str::<MotorStatus>(&json).unwrap(), status);
+
+        let telemetry = Telemetry {
+            status_flags: 0x0204,
+            input_voltage: 12.5,
+            motors: vec![
+                MotorTelemetry {
+                    speed: 0.5,
+                    current: Some(100),
+                },
+                MotorTelemetry {
+                    speed: -0.25,
+                    current: None,
+                },
+            ],
+        };
+        let json = serde_json::to_string(&telemetry).unwrap();
+        assert_eq!(serde_json::from_str::<Telemetry>(&json).unwrap(), telemetry);
+    }
+}
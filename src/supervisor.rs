@@ -0,0 +1,116 @@
+use crate::{Device, Error};
+use embedded_hal::i2c::I2c;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// A [`Device`] being watched over by a background thread. The thread periodically resets the
+/// controller's command timeout so the motors keep their speeds, and if too many of those
+/// keepalives fail in a row, it coasts the motors and reports the last error through
+/// [`SupervisedDevice::failures`]. You can create one with [`Device::supervise`], or straight
+/// away with [`DeviceBuilder::supervised`](crate::DeviceBuilder::supervised).
+///
+/// The device itself is shared between your thread and the supervisor thread, so you need to
+/// call [`SupervisedDevice::lock`] to use it. Keep in mind the supervisor can't send keepalives
+/// while you're holding the lock.
+///
+/// Dropping this stops the supervisor thread (without coasting the motors, unless the device was
+/// built with [`DeviceBuilder::coast_on_drop`](crate::DeviceBuilder::coast_on_drop)) and closes
+/// the device.
+pub struct SupervisedDevice<I: I2c> {
+    device: Arc<Mutex<Device<I>>>,
+    failures: Receiver<Error>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl<I: I2c + Send + 'static> SupervisedDevice<I> {
+    pub(crate) fn new(
+        device: Device<I>,
+        keepalive_interval: Duration,
+        max_consecutive_errors: u32,
+    ) -> SupervisedDevice<I> {
+        let device = Arc::new(Mutex::new(device));
+        let stop = Arc::new(AtomicBool::new(false));
+        let (failures_tx, failures) = mpsc::channel();
+        let thread = {
+            let device = device.clone();
+            let stop = stop.clone();
+            std::thread::spawn(move || {
+                supervise(
+                    &device,
+                    &stop,
+                    &failures_tx,
+                    keepalive_interval,
+                    max_consecutive_errors,
+                )
+            })
+        };
+        SupervisedDevice {
+            device,
+            failures,
+            stop,
+            thread: Some(thread),
+        }
+    }
+
+    /// Locks the device so you can send it commands. The supervisor thread will block on its next
+    /// keepalive until the returned guard is dropped.
+    pub fn lock(&self) -> MutexGuard<'_, Device<I>> {
+        lock(&self.device)
+    }
+
+    /// This is where the supervisor reports that it gave up. If the keepalives fail the configured
+    /// number of times in a row, the motors get coasted, the last error is sent on this channel,
+    /// and the supervisor thread exits. You can poll it with [`Receiver::try_recv`] in your control
+    /// loop, or block on it from a dedicated thread with [`Receiver::recv`].
+    pub fn failures(&self) -> &Receiver<Error> {
+        &self.failures
+    }
+}
+
+impl<I: I2c> Drop for SupervisedDevice<I> {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            thread.thread().unpark();
+            let _ = thread.join();
+        }
+    }
+}
+
+fn supervise<I: I2c>(
+    device: &Mutex<Device<I>>,
+    stop: &AtomicBool,
+    failures: &Sender<Error>,
+    keepalive_interval: Duration,
+    max_consecutive_errors: u32,
+) {
+    let mut consecutive_errors = 0;
+    loop {
+        std::thread::park_timeout(keepalive_interval);
+        if stop.load(Ordering::Relaxed) {
+            return;
+        }
+
+        let mut device = lock(device);
+        match device.feed() {
+            Ok(()) => consecutive_errors = 0,
+            Err(e) => {
+                consecutive_errors += 1;
+                if consecutive_errors >= max_consecutive_errors {
+                    // Best effort, the bus is likely in a bad state already
+                    let _ = device.coast_now();
+                    let _ = failures.send(e);
+                    return;
+                }
+            }
+        }
+    }
+}
+
+fn lock<I: I2c>(device: &Mutex<Device<I>>) -> MutexGuard<'_, Device<I>> {
+    device.lock().unwrap_or_else(PoisonError::into_inner)
+}
@@ -0,0 +1,94 @@
+/// A controller variable that can be read with
+/// [`Device::get_variable_typed`](crate::Device::get_variable_typed) and written with
+/// [`Device::set_variable_typed`](crate::Device::set_variable_typed). Most of these are per-motor
+/// variables, but a few are general (device-wide) ones, which need to be read and written with motor 0.
+/// For more details on what each of them does, see the
+/// [variable reference](https://www.pololu.com/docs/0J84).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Variable {
+    /// (General) How long the controller waits for a command before stopping the motors, in units
+    /// of 4 ms.
+    CommandTimeout,
+    /// (General) What the controller does to the motors when an error happens.
+    ErrorResponse,
+    /// (General) Which status flags count as errors.
+    ErrorMask,
+    /// The PWM frequency mode of the motor.
+    PwmMode,
+    /// The maximum acceleration when driving forward, in speed units per update period.
+    MaxAccelerationForward,
+    /// The maximum acceleration when driving in reverse, in speed units per update period.
+    MaxAccelerationReverse,
+    /// The maximum deceleration when driving forward, in speed units per update period.
+    MaxDecelerationForward,
+    /// The maximum deceleration when driving in reverse, in speed units per update period.
+    MaxDecelerationReverse,
+    /// The smallest non-zero speed the motor is driven at when going forward.
+    StartingSpeedForward,
+    /// The smallest non-zero speed the motor is driven at when going in reverse.
+    StartingSpeedReverse,
+    /// How long the motor is stopped for when switching from forward to reverse, in update
+    /// periods.
+    DirectionChangeDelayForward,
+    /// How long the motor is stopped for when switching from reverse to forward, in update
+    /// periods.
+    DirectionChangeDelayReverse,
+    /// The current limit of the motor, only on controllers that limit current.
+    CurrentLimit,
+    /// The offset subtracted from the raw current sense reading.
+    CurrentSenseOffset,
+    /// The minimum divisor used when processing the current sense reading.
+    CurrentSenseMinimumDivisor,
+}
+
+impl Variable {
+    /// The offset of this variable, within either the general or the per-motor variable space.
+    pub fn offset(&self) -> u8 {
+        match self {
+            Variable::CommandTimeout => 5,
+            Variable::ErrorResponse => 7,
+            Variable::ErrorMask => 8,
+            Variable::PwmMode => 1,
+            Variable::MaxAccelerationForward => 10,
+            Variable::MaxAccelerationReverse => 12,
+            Variable::MaxDecelerationForward => 14,
+            Variable::MaxDecelerationReverse => 16,
+            Variable::StartingSpeedForward => 18,
+            Variable::StartingSpeedReverse => 20,
+            Variable::DirectionChangeDelayForward => 22,
+            Variable::DirectionChangeDelayReverse => 23,
+            Variable::CurrentLimit => 24,
+            Variable::CurrentSenseOffset => 32,
+            Variable::CurrentSenseMinimumDivisor => 33,
+        }
+    }
+
+    /// How many bytes long this variable is. Two-byte variables are stored little-endian.
+    pub fn byte_len(&self) -> u8 {
+        match self {
+            Variable::CommandTimeout
+            | Variable::ErrorMask
+            | Variable::MaxAccelerationForward
+            | Variable::MaxAccelerationReverse
+            | Variable::MaxDecelerationForward
+            | Variable::MaxDecelerationReverse
+            | Variable::StartingSpeedForward
+            | Variable::StartingSpeedReverse
+            | Variable::CurrentLimit
+            | Variable::CurrentSenseMinimumDivisor => 2,
+            Variable::ErrorResponse
+            | Variable::PwmMode
+            | Variable::DirectionChangeDelayForward
+            | Variable::DirectionChangeDelayReverse
+            | Variable::CurrentSenseOffset => 1,
+        }
+    }
+
+    /// Whether this is a general (device-wide) variable, as opposed to a per-motor one.
+    pub fn is_general(&self) -> bool {
+        matches!(
+            self,
+            Variable::CommandTimeout | Variable::ErrorResponse | Variable::ErrorMask
+        )
+    }
+}